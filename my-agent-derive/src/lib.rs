@@ -0,0 +1,385 @@
+//! my-agent 的派生宏
+//!
+//! `#[derive(Tool)]` 作用于工具的参数结构，根据字段和文档注释生成工具名称、说明、
+//! 参数的 JSON Schema 以及参数解析，保证提供给大模型的 Schema 与实际解析的结构一致。
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::{
+    meta::ParseNestedMeta, parse_macro_input, token, Attribute, Data, DeriveInput, Error, Expr,
+    ExprArray, ExprLit, Fields, Lit, LitStr, Meta, Result, Token,
+};
+
+/// 为工具的参数结构实现 `my_agent::tools::ToolArgs`
+///
+/// - 工具名称默认为去掉 `Args` 后缀的结构名称的 snake_case 形式，可以用
+///   `#[tool(name = "...")]` 指定
+/// - 结构的文档注释作为工具说明，字段的文档注释作为参数说明
+/// - 参数类型由字段类型的 `ToolParameter` 实现决定，`Option<T>` 字段为可选参数
+/// - 遵循 serde 的 `rename`、`rename_all`、`default` 和 `skip` 属性，保证 Schema 与反序列化
+///   一致，无法在 Schema 中表达的 serde 属性（例如 `flatten`）会报编译错误
+/// - 字段上的 `#[tool(enum = [...], min_length = N, max_length = N, minimum = N, maximum = N)]`
+///   写入参数 Schema，调用工具前按 Schema 校验
+///
+/// 参数结构还需要实现 `serde::Deserialize`
+#[proc_macro_derive(Tool, attributes(tool))]
+pub fn derive_tool(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> Result<proc_macro2::TokenStream> {
+    let ident = &input.ident;
+
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(ident, "Tool 只能用于结构体"));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(Error::new_spanned(ident, "Tool 只能用于具名字段的结构体"));
+    };
+
+    let name = match tool_name(&input.attrs)? {
+        Some(name) => name.value(),
+        None => snake_case(ident.to_string().trim_end_matches("Args")),
+    };
+    let description = doc_comment(&input.attrs)
+        .ok_or_else(|| Error::new_spanned(ident, "请使用文档注释说明工具的用途"))?;
+
+    let container = SerdeContainer::parse(&input.attrs)?;
+
+    let parameters = fields
+        .named
+        .iter()
+        .filter_map(|field| -> Option<Result<_>> {
+            let serde = match SerdeField::parse(&field.attrs) {
+                Ok(serde) => serde,
+                Err(e) => return Some(Err(e)),
+            };
+            if serde.skip {
+                return None;
+            }
+
+            let field_name = field.ident.as_ref().unwrap().to_string();
+            let field_name = match serde.rename {
+                Some(rename) => rename,
+                None => container.rename_all(field_name.trim_start_matches("r#")),
+            };
+            let has_default = container.default || serde.default;
+            let ty = &field.ty;
+            let description = match doc_comment(&field.attrs) {
+                Some(description) => quote!(::std::option::Option::Some(#description)),
+                None => quote!(::std::option::Option::None),
+            };
+            let constraints = match constraints(&field.attrs) {
+                Ok(constraints) => constraints,
+                Err(e) => return Some(Err(e)),
+            }
+            .into_iter()
+            .map(|(keyword, value)| quote!((#keyword, #value)));
+
+            Some(Ok(quote! {
+                (
+                    #field_name,
+                    #description,
+                    ::my_agent::tools::__private::constrain(
+                        <#ty as ::my_agent::tools::ToolParameter>::schema(),
+                        ::std::vec![#(#constraints),*],
+                    ),
+                    <#ty as ::my_agent::tools::ToolParameter>::OPTIONAL || #has_default,
+                )
+            }))
+        });
+    let parameters = parameters.collect::<Result<Vec<_>>>()?;
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::my_agent::tools::ToolArgs for #ident #ty_generics #where_clause {
+            const NAME: &'static str = #name;
+            const DESCRIPTION: &'static str = #description;
+
+            fn schema() -> ::my_agent::tools::__private::Value {
+                ::my_agent::tools::__private::object_schema(::std::vec![#(#parameters),*])
+            }
+        }
+    })
+}
+
+// #[tool(name = "...")]
+fn tool_name(attrs: &[Attribute]) -> Result<Option<LitStr>> {
+    let mut name = None;
+
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("tool")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                name = Some(meta.value()?.parse::<LitStr>()?);
+                Ok(())
+            } else {
+                Err(meta.error("不支持的 tool 属性"))
+            }
+        })?;
+    }
+
+    Ok(name)
+}
+
+/// 结构上影响参数名称和是否必填的 serde 属性
+#[derive(Default)]
+struct SerdeContainer {
+    rename_all: Option<(LitStr, RenameRule)>,
+    default: bool,
+}
+
+impl SerdeContainer {
+    fn parse(attrs: &[Attribute]) -> Result<Self> {
+        let mut container = Self::default();
+
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename_all") {
+                    if let Some(rule) = deserialize_name(&meta)? {
+                        container.rename_all = Some((rule.clone(), RenameRule::parse(&rule)?));
+                    }
+                } else if meta.path.is_ident("default") {
+                    skip_value(&meta)?;
+                    container.default = true;
+                } else if ["rename", "deny_unknown_fields", "bound"]
+                    .iter()
+                    .any(|name| meta.path.is_ident(name))
+                {
+                    // 不影响参数的 Schema
+                    skip_value(&meta)?;
+                } else {
+                    return Err(meta.error("Tool 不支持此 serde 属性"));
+                }
+                Ok(())
+            })?;
+        }
+
+        Ok(container)
+    }
+
+    fn rename_all(&self, name: &str) -> String {
+        match &self.rename_all {
+            Some((_, rule)) => rule.apply(name),
+            None => name.to_string(),
+        }
+    }
+}
+
+/// 字段上影响参数名称和是否必填的 serde 属性
+#[derive(Default)]
+struct SerdeField {
+    rename: Option<String>,
+    default: bool,
+    skip: bool,
+}
+
+impl SerdeField {
+    fn parse(attrs: &[Attribute]) -> Result<Self> {
+        let mut field = Self::default();
+
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    field.rename = deserialize_name(&meta)?.map(|name| name.value());
+                } else if meta.path.is_ident("default") {
+                    skip_value(&meta)?;
+                    field.default = true;
+                } else if meta.path.is_ident("skip") || meta.path.is_ident("skip_deserializing") {
+                    field.skip = true;
+                } else if [
+                    "alias",
+                    "skip_serializing",
+                    "skip_serializing_if",
+                    "serialize_with",
+                    "bound",
+                ]
+                .iter()
+                .any(|name| meta.path.is_ident(name))
+                {
+                    // 不影响反序列化时接受的参数
+                    skip_value(&meta)?;
+                } else {
+                    return Err(meta.error("Tool 不支持此 serde 属性"));
+                }
+                Ok(())
+            })?;
+        }
+
+        Ok(field)
+    }
+}
+
+// rename = "..." 或 rename(serialize = "...", deserialize = "...")，只取反序列化使用的名称
+fn deserialize_name(meta: &ParseNestedMeta) -> Result<Option<LitStr>> {
+    if meta.input.peek(Token![=]) {
+        return Ok(Some(meta.value()?.parse()?));
+    }
+
+    let mut name = None;
+    meta.parse_nested_meta(|meta| {
+        if meta.path.is_ident("deserialize") {
+            name = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("serialize") {
+            meta.value()?.parse::<LitStr>()?;
+        } else {
+            return Err(meta.error("应为 serialize 或 deserialize"));
+        }
+        Ok(())
+    })?;
+
+    Ok(name)
+}
+
+// 跳过不需要的属性值，例如 default = "path" 或 bound(...)
+fn skip_value(meta: &ParseNestedMeta) -> Result<()> {
+    if meta.input.peek(Token![=]) {
+        meta.value()?.parse::<Expr>()?;
+    } else if meta.input.peek(token::Paren) {
+        meta.parse_nested_meta(|meta| skip_value(&meta))?;
+    }
+
+    Ok(())
+}
+
+/// serde 的 rename_all 规则，字段名称按 snake_case 转换
+enum RenameRule {
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
+}
+
+impl RenameRule {
+    fn parse(rule: &LitStr) -> Result<Self> {
+        Ok(match rule.value().as_str() {
+            "lowercase" => Self::Lower,
+            "UPPERCASE" => Self::Upper,
+            "PascalCase" => Self::Pascal,
+            "camelCase" => Self::Camel,
+            "snake_case" => Self::Snake,
+            "SCREAMING_SNAKE_CASE" => Self::ScreamingSnake,
+            "kebab-case" => Self::Kebab,
+            "SCREAMING-KEBAB-CASE" => Self::ScreamingKebab,
+            _ => return Err(Error::new_spanned(rule, "未知的 rename_all 规则")),
+        })
+    }
+
+    fn apply(&self, field: &str) -> String {
+        match self {
+            Self::Lower | Self::Snake => field.to_string(),
+            Self::Upper | Self::ScreamingSnake => field.to_ascii_uppercase(),
+            Self::Pascal => field
+                .split('_')
+                .map(|word| {
+                    let mut chars = word.chars();
+                    chars
+                        .next()
+                        .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+                        .unwrap_or_default()
+                })
+                .collect(),
+            Self::Camel => {
+                let pascal = Self::Pascal.apply(field);
+                let mut chars = pascal.chars();
+                chars
+                    .next()
+                    .map(|first| first.to_ascii_lowercase().to_string() + chars.as_str())
+                    .unwrap_or_default()
+            }
+            Self::Kebab => field.replace('_', "-"),
+            Self::ScreamingKebab => field.replace('_', "-").to_ascii_uppercase(),
+        }
+    }
+}
+
+// 字段上的 #[tool(...)]，对应参数 Schema 中的校验关键字
+fn constraints(attrs: &[Attribute]) -> Result<Vec<(&'static str, proc_macro2::TokenStream)>> {
+    let mut constraints = Vec::new();
+
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("tool")) {
+        attr.parse_nested_meta(|meta| {
+            let keyword = if meta.path.is_ident("enum") {
+                let values = meta.value()?.parse::<ExprArray>()?.elems;
+                constraints.push((
+                    "enum",
+                    quote!(::my_agent::tools::__private::Value::from(
+                        ::std::vec![#values]
+                    )),
+                ));
+                return Ok(());
+            } else if meta.path.is_ident("min_length") {
+                "minLength"
+            } else if meta.path.is_ident("max_length") {
+                "maxLength"
+            } else if meta.path.is_ident("minimum") {
+                "minimum"
+            } else if meta.path.is_ident("maximum") {
+                "maximum"
+            } else {
+                return Err(meta.error("不支持的 tool 属性"));
+            };
+
+            let value = meta.value()?.parse::<Lit>()?;
+            constraints.push((
+                keyword,
+                quote!(::my_agent::tools::__private::Value::from(#value)),
+            ));
+            Ok(())
+        })?;
+    }
+
+    Ok(constraints)
+}
+
+// 多行文档注释按行拼接，去掉每行开头的一个空格
+fn doc_comment(attrs: &[Attribute]) -> Option<LitStr> {
+    let lines = attrs
+        .iter()
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(meta) if meta.path.is_ident("doc") => match &meta.value {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(line),
+                    ..
+                }) => Some(line.value()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .map(|line| {
+            line.strip_prefix(' ')
+                .unwrap_or(&line)
+                .trim_end()
+                .to_string()
+        })
+        .collect::<Vec<_>>();
+
+    let doc = lines.join("\n").trim().to_string();
+    (!doc.is_empty()).then(|| LitStr::new(&doc, Span::call_site()))
+}
+
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+
+    snake
+}
@@ -0,0 +1,175 @@
+use async_openai::types::CompletionUsage;
+use serde::{Deserialize, Serialize};
+use std::ops::AddAssign;
+
+/// 单次运行的预算，None 表示不限制
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Budget {
+    pub max_prompt_tokens: Option<u64>,
+    pub max_completion_tokens: Option<u64>,
+    // 按价格表估算的费用上限
+    pub max_cost: Option<f64>,
+}
+
+impl Budget {
+    /// 假设下一次请求的用量与 last 相当，预测继续运行是否会超出预算，返回超出的原因
+    pub fn exceeded_by(&self, used: &RunUsage, last: &RunUsage) -> Option<String> {
+        if let Some(max) = self.max_prompt_tokens {
+            if used.prompt_tokens + last.prompt_tokens > max {
+                return Some(format!(
+                    "输入 token 预算即将用尽（已使用 {}，上限 {}）",
+                    used.prompt_tokens, max
+                ));
+            }
+        }
+
+        if let Some(max) = self.max_completion_tokens {
+            if used.completion_tokens + last.completion_tokens > max {
+                return Some(format!(
+                    "输出 token 预算即将用尽（已使用 {}，上限 {}）",
+                    used.completion_tokens, max
+                ));
+            }
+        }
+
+        if let Some(max) = self.max_cost {
+            if used.cost + last.cost > max {
+                return Some(format!(
+                    "费用预算即将用尽（已使用 {:.4}，上限 {:.4}）",
+                    used.cost, max
+                ));
+            }
+        }
+
+        None
+    }
+}
+
+/// 模型价格，单位为每百万 token 的费用
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelPrice {
+    pub prompt: f64,
+    pub completion: f64,
+}
+
+impl ModelPrice {
+    pub fn new(prompt: f64, completion: f64) -> Self {
+        Self { prompt, completion }
+    }
+}
+
+/// 累计的 token 用量和估算费用
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RunUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cost: f64,
+}
+
+impl RunUsage {
+    /// 没有价格时费用记为 0
+    pub fn new(usage: Option<&CompletionUsage>, price: Option<&ModelPrice>) -> Self {
+        let Some(usage) = usage else {
+            return Self::default();
+        };
+
+        let prompt_tokens = usage.prompt_tokens as u64;
+        let completion_tokens = usage.completion_tokens as u64;
+        let cost = price
+            .map(|price| {
+                (prompt_tokens as f64 * price.prompt + completion_tokens as f64 * price.completion)
+                    / 1_000_000.0
+            })
+            .unwrap_or_default();
+
+        Self {
+            prompt_tokens,
+            completion_tokens,
+            cost,
+        }
+    }
+}
+
+impl AddAssign for RunUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.cost += other.cost;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(prompt_tokens: u32, completion_tokens: u32) -> CompletionUsage {
+        CompletionUsage {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+        }
+    }
+
+    #[test]
+    fn test_run_usage() {
+        let price = ModelPrice::new(10.0, 20.0);
+
+        let mut total = RunUsage::default();
+        total += RunUsage::new(Some(&usage(1_000, 500)), Some(&price));
+        total += RunUsage::new(Some(&usage(2_000, 500)), Some(&price));
+        total += RunUsage::new(None, Some(&price));
+
+        assert_eq!(total.prompt_tokens, 3_000);
+        assert_eq!(total.completion_tokens, 1_000);
+        assert!((total.cost - 0.05).abs() < 1e-9);
+
+        let unpriced = RunUsage::new(Some(&usage(1_000, 500)), None);
+        assert_eq!(unpriced.cost, 0.0);
+    }
+
+    #[test]
+    fn test_budget_exceeded_by() {
+        let used = RunUsage {
+            prompt_tokens: 3_000,
+            completion_tokens: 300,
+            cost: 0.5,
+        };
+        let last = RunUsage {
+            prompt_tokens: 1_500,
+            completion_tokens: 100,
+            cost: 0.2,
+        };
+
+        assert_eq!(Budget::default().exceeded_by(&used, &last), None);
+
+        let budget = Budget {
+            max_prompt_tokens: Some(5_000),
+            ..Default::default()
+        };
+        assert_eq!(budget.exceeded_by(&used, &last), None);
+
+        let budget = Budget {
+            max_prompt_tokens: Some(4_000),
+            ..Default::default()
+        };
+        assert!(budget
+            .exceeded_by(&used, &last)
+            .unwrap()
+            .contains("输入 token"));
+
+        let budget = Budget {
+            max_completion_tokens: Some(350),
+            ..Default::default()
+        };
+        assert!(budget
+            .exceeded_by(&used, &last)
+            .unwrap()
+            .contains("输出 token"));
+
+        let budget = Budget {
+            max_cost: Some(0.6),
+            ..Default::default()
+        };
+        assert!(budget.exceeded_by(&used, &last).unwrap().contains("费用"));
+    }
+}
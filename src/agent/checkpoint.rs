@@ -0,0 +1,103 @@
+use super::{ReActAgentConfig, RunUsage};
+use anyhow::{Context, Result};
+use async_openai::types::{ChatCompletionMessageToolCall, ChatCompletionRequestMessage};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+
+/// 运行中的 Agent 状态，每轮结束时写入检查点文件，进程重启后可以从中恢复
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub(crate) config: ReActAgentConfig,
+    // 本轮对话的问题
+    pub(crate) question: String,
+    // 短期记忆中的全部消息，包含系统消息
+    pub(crate) messages: Vec<ChatCompletionRequestMessage>,
+    // 恢复后开始的轮数
+    pub(crate) step: usize,
+    pub(crate) tool_failures: usize,
+    // 已使用的 token 和费用，恢复后继续累计
+    #[serde(default)]
+    pub(crate) usage: RunUsage,
+    // 大模型已发起但尚未执行完成的工具调用，恢复后首先执行
+    pub(crate) pending_tool_calls: Vec<ChatCompletionMessageToolCall>,
+}
+
+impl Checkpoint {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("读取检查点失败: {}", path.display()))?;
+
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// 先写入临时文件再重命名，避免写入过程中进程退出导致检查点损坏
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let temp = path.with_extension("tmp");
+        fs::write(&temp, serde_json::to_string_pretty(self)?)?;
+        fs::rename(&temp, path).with_context(|| format!("保存检查点失败: {}", path.display()))?;
+
+        Ok(())
+    }
+
+    pub fn question(&self) -> &str {
+        &self.question
+    }
+
+    pub fn step(&self) -> usize {
+        self.step
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_openai::types::ChatCompletionRequestUserMessageArgs;
+
+    #[test]
+    fn test_checkpoint_save_and_load() -> Result<()> {
+        let config = ReActAgentConfig::builder()
+            .set_api_key("my_api_key")
+            .set_model("moonshot-v1-8k")
+            .try_set_base_url("http://localhost")?
+            .build()?;
+
+        let checkpoint = Checkpoint {
+            config,
+            question: "问题".to_string(),
+            messages: vec![ChatCompletionRequestUserMessageArgs::default()
+                .content("问题")
+                .build()?
+                .into()],
+            step: 3,
+            tool_failures: 1,
+            usage: RunUsage::default(),
+            pending_tool_calls: Vec::new(),
+        };
+
+        let path =
+            std::env::temp_dir().join(format!("my-agent-checkpoint-{}.json", std::process::id()));
+        checkpoint.save(&path)?;
+
+        // 密钥不写入检查点
+        let content = fs::read_to_string(&path)?;
+        assert!(!content.contains("my_api_key"));
+
+        let loaded = Checkpoint::load(&path)?;
+        fs::remove_file(&path)?;
+
+        assert_eq!(loaded.config.api_key, "");
+        assert_eq!(loaded.config.model, "moonshot-v1-8k");
+        assert_eq!(loaded.question(), "问题");
+        assert_eq!(loaded.messages, checkpoint.messages);
+        assert_eq!(loaded.step(), 3);
+        assert_eq!(loaded.tool_failures, 1);
+
+        Ok(())
+    }
+}
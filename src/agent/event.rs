@@ -0,0 +1,109 @@
+use super::RunUsage;
+use crate::tools::{ToolErrorKind, ToolSource};
+use anyhow::Result;
+use async_openai::types::{
+    ChatCompletionMessageToolCall, ChatCompletionRequestMessage, CompletionUsage,
+};
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+use std::pin::Pin;
+
+pub type AgentEventStream = Pin<Box<dyn Stream<Item = Result<AgentEvent>> + Send>>;
+
+/// Agent 运行过程中产生的事件，供界面展示和日志记录使用
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AgentEvent {
+    /// 用户消息：运行开始时的问题，或助手回复为空时重新提示的问题
+    UserMessage { content: String },
+    /// 从检查点恢复运行，从第 step 轮继续
+    Resumed { step: usize },
+    /// 开始第 step 轮推理
+    StepStarted { step: usize, max_steps: usize },
+    /// 流式输出时模型新生成的文本片段
+    ContentDelta { step: usize, delta: String },
+    /// 短期记忆超出上下文上限，evicted 条最早的消息被移除，并由 summary 替代，
+    /// 总结失败时 summary 为 None，这些消息被直接丢弃
+    MemoryCompacted {
+        evicted: usize,
+        summary: Option<String>,
+    },
+    /// 大模型返回了完整回复，content 为助手的思考内容，tool_calls 为需要调用的工具
+    ModelResponded {
+        content: Option<String>,
+        tool_calls: Vec<ChatCompletionMessageToolCall>,
+        usage: Option<CompletionUsage>,
+    },
+    /// 开始调用工具
+    ToolCallStarted {
+        id: String,
+        name: String,
+        arguments: String,
+    },
+    /// 工具调用成功，sources 为结果引用的来源，例如搜索到的网页
+    ToolCallFinished {
+        id: String,
+        name: String,
+        result: String,
+        sources: Vec<ToolSource>,
+    },
+    /// 工具解析或执行失败，错误信息已作为工具消息返回给大模型
+    ToolFailed {
+        id: String,
+        name: String,
+        kind: ToolErrorKind,
+        error: String,
+    },
+    /// 提示词模式下第 step 轮的回复无法解析为命令，错误信息已作为用户消息返回给大模型
+    InvalidResponse { step: usize, error: String },
+    /// 请求大模型失败，等待 delay_ms 毫秒后进行第 attempt + 1 次请求
+    Retrying {
+        step: usize,
+        attempt: usize,
+        delay_ms: u64,
+        error: String,
+    },
+    /// 结束工具给出的最终答案
+    FinalAnswer { answer: String },
+    /// 预算即将用尽，接下来要求大模型根据已有信息给出最终答案
+    BudgetExceeded { reason: String, usage: RunUsage },
+    /// 达到最大调用轮数仍未完成任务，接下来要求大模型根据已有信息给出最终答案
+    MaxStepsReached { max_steps: usize },
+    /// 运行结束，status 为结束的原因，始终是事件流的最后一个事件
+    RunFinished { status: RunStatus },
+    /// 运行在第 step 轮被取消，transcript 为取消时已有的对话记录
+    Cancelled {
+        step: usize,
+        transcript: Vec<ChatCompletionRequestMessage>,
+    },
+}
+
+/// 运行结束的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStatus {
+    /// 大模型调用结束工具给出了答案
+    Completed,
+    /// 达到最大调用轮数
+    StepLimit,
+    /// 预算即将用尽
+    Budget,
+    /// 运行被取消
+    Cancelled,
+    /// 运行出错
+    Error,
+}
+
+impl RunStatus {
+    /// 事件决定的结束原因，第一个决定结束原因的事件之后的错误不改变结束原因，例如收尾请求失败
+    pub(crate) fn from_event(event: &Result<AgentEvent>) -> Option<Self> {
+        match event {
+            Ok(AgentEvent::FinalAnswer { .. }) => Some(Self::Completed),
+            Ok(AgentEvent::MaxStepsReached { .. }) => Some(Self::StepLimit),
+            Ok(AgentEvent::BudgetExceeded { .. }) => Some(Self::Budget),
+            Ok(AgentEvent::Cancelled { .. }) => Some(Self::Cancelled),
+            Ok(_) => None,
+            Err(_) => Some(Self::Error),
+        }
+    }
+}
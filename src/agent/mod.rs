@@ -1,9 +1,20 @@
+mod budget;
+mod checkpoint;
+mod event;
 mod language;
 mod react_agent;
 mod react_agent_config;
 pub(crate) mod response;
+mod session;
+mod tool_mode;
 
+pub use budget::{Budget, ModelPrice, RunUsage};
+pub use checkpoint::Checkpoint;
+pub use event::{AgentEvent, AgentEventStream, RunStatus};
 pub(crate) use language::Language;
 pub use react_agent::ReActAgent;
 pub use react_agent_config::ReActAgentConfig;
 pub use response::Response;
+pub use session::Session;
+pub use tokio_util::sync::CancellationToken;
+pub use tool_mode::ToolMode;
@@ -1,128 +1,956 @@
-use super::ReActAgentConfig;
+use super::{
+    response::Command, AgentEvent, AgentEventStream, CancellationToken, Checkpoint,
+    ReActAgentConfig, RunStatus, RunUsage, Session, ToolMode,
+};
 use crate::{
-    memory::ShortMemory,
+    history::{RunHistory, RunRecorder},
+    llm::{classify, BackendError, ChatBackend, ChatStreamAssembler, ErrorClass, OpenAIBackend},
+    memory::{ApproxTokenizer, LongMemory, MemoryKind, MemoryStrategy, ShortMemory, Tokenizer},
     planning::Planning,
-    tools::{ToolExector, Tools},
+    tools::{run_tool_calls, ToolCall, ToolError, ToolOutcome, ToolOutput, ToolRegistry},
 };
-use anyhow::Result;
-use async_openai::{
-    config::OpenAIConfig,
-    types::{
-        ChatCompletionRequestAssistantMessageArgs, ChatCompletionRequestMessage,
-        ChatCompletionRequestToolMessageArgs,
-    },
-    Client,
+use anyhow::{anyhow, Context, Result};
+use async_openai::types::{
+    ChatCompletionMessageToolCall, ChatCompletionRequestAssistantMessageArgs,
+    ChatCompletionRequestMessage, ChatCompletionRequestToolMessageArgs,
+    ChatCompletionRequestUserMessage, ChatCompletionToolType, CreateChatCompletionResponse,
+    FunctionCall,
 };
 use async_stream::stream;
-use futures::Stream;
-use std::pin::Pin;
+use futures::{Stream, StreamExt};
+use std::{future::Future, ops::DerefMut, sync::Arc};
 
-type MessageStream = Pin<Box<dyn Stream<Item = Result<ChatCompletionRequestMessage>> + Send>>;
+pub struct ReActAgent<B = OpenAIBackend> {
+    pub(crate) config: ReActAgentConfig,
+    backend: Arc<B>,
+    tokenizer: Arc<dyn Tokenizer>,
+    long_memory: Option<Arc<LongMemory>>,
+    history: Option<Arc<RunHistory>>,
+    pub(crate) tools: ToolRegistry,
+}
 
-#[derive(Clone)]
-pub struct ReActAgent {
-    config: ReActAgentConfig,
-    client: Client<OpenAIConfig>,
+impl<B> Clone for ReActAgent<B> {
+    fn clone(&self) -> Self {
+        Self {
+            config: self.config.clone(),
+            backend: self.backend.clone(),
+            tokenizer: self.tokenizer.clone(),
+            long_memory: self.long_memory.clone(),
+            history: self.history.clone(),
+            tools: self.tools.clone(),
+        }
+    }
 }
 
-impl ReActAgent {
+impl ReActAgent<OpenAIBackend> {
     pub fn new(config: ReActAgentConfig) -> Self {
-        let openai_config = OpenAIConfig::new()
-            .with_api_key(config.api_key.as_str())
-            .with_api_base(config.base_url.as_str());
+        let backend = OpenAIBackend::new(config.api_key.as_str(), config.base_url.as_str());
 
-        let client = Client::with_config(openai_config);
+        Self::with_backend(config, backend)
+    }
+}
 
-        Self { config, client }
+impl<B> ReActAgent<B>
+where
+    B: ChatBackend + 'static,
+{
+    /// 使用自定义的大模型后端，例如其他厂商的接口或测试用的替身
+    pub fn with_backend(config: ReActAgentConfig, backend: B) -> Self {
+        Self {
+            config,
+            backend: Arc::new(backend),
+            tokenizer: Arc::new(ApproxTokenizer),
+            long_memory: None,
+            history: None,
+            tools: ToolRegistry::builtin(),
+        }
     }
 
-    pub async fn invoke(self, question: &str) -> Result<MessageStream> {
-        let language = self.config.language.to_string();
-        let planning = Planning::try_new()?;
-        let mut short_memory = ShortMemory::new();
+    /// 使用与模型一致的分词器计算短期记忆的 token 数
+    pub fn with_tokenizer(mut self, tokenizer: impl Tokenizer + 'static) -> Self {
+        self.tokenizer = Arc::new(tokenizer);
+        self
+    }
+
+    /// 使用长期记忆：每轮开始时检索相关记忆写入系统消息，任务完成后保存问题和答案
+    ///
+    /// memory_save 和 memory_search 工具也读写这个长期记忆
+    pub fn with_long_memory(mut self, long_memory: Arc<LongMemory>) -> Self {
+        self.tools = self.tools.with_long_memory(&long_memory);
+        self.long_memory = Some(long_memory);
+        self
+    }
+
+    /// 使用自定义的工具集合，默认为全部内置工具
+    ///
+    /// 设置了长期记忆时，其中的 memory_save 和 memory_search 替换为读写该长期记忆的内置工具
+    pub fn with_tools(mut self, tools: ToolRegistry) -> Self {
+        self.tools = match &self.long_memory {
+            Some(long_memory) => tools.with_long_memory(long_memory),
+            None => tools,
+        };
+        self
+    }
+
+    /// 每次运行结束后将问题、对话记录、工具调用和用量写入运行记录
+    pub fn with_history(mut self, history: Arc<RunHistory>) -> Self {
+        self.history = Some(history);
+        self
+    }
+
+    /// 开启多轮对话，短期记忆在各轮之间保留
+    pub fn session(self) -> Session<B> {
+        Session::new(self)
+    }
+
+    /// 运行智能体，丢弃返回的事件流即可取消运行，包括正在执行的工具调用
+    pub async fn invoke(self, question: &str) -> Result<AgentEventStream> {
+        self.invoke_with_cancellation(question, CancellationToken::new())
+            .await
+    }
+
+    /// 运行智能体，cancel 被取消时中止正在进行的大模型请求和工具调用，
+    /// 并以包含已有对话记录的 Cancelled 事件结束事件流
+    pub async fn invoke_with_cancellation(
+        self,
+        question: &str,
+        cancel: CancellationToken,
+    ) -> Result<AgentEventStream> {
+        self.session()
+            .send_with_cancellation(question, cancel)
+            .await
+    }
+
+    /// 从检查点继续运行，使用检查点中保存的配置，密钥和后端沿用当前 Agent
+    pub async fn resume(self, checkpoint: Checkpoint) -> Result<AgentEventStream> {
+        self.resume_with_cancellation(checkpoint, CancellationToken::new())
+            .await
+    }
+
+    pub async fn resume_with_cancellation(
+        self,
+        checkpoint: Checkpoint,
+        cancel: CancellationToken,
+    ) -> Result<AgentEventStream> {
+        let agent = Self {
+            config: ReActAgentConfig {
+                api_key: self.config.api_key,
+                ..checkpoint.config
+            },
+            backend: self.backend,
+            tokenizer: self.tokenizer,
+            long_memory: self.long_memory,
+            history: self.history,
+            tools: self.tools,
+        };
+
+        let mut short_memory = agent.short_memory();
+        for message in checkpoint.messages {
+            short_memory.append(message);
+        }
+
+        let state = RunState {
+            step: checkpoint.step,
+            tool_failures: checkpoint.tool_failures,
+            usage: checkpoint.usage,
+            pending_tool_calls: checkpoint.pending_tool_calls,
+            resumed: true,
+        };
 
-        short_memory.append(planning.build_system_message(question, &language)?.into());
+        agent.run(
+            Planning::try_new()?.with_tool_mode(agent.config.tool_mode),
+            Box::new(short_memory),
+            &checkpoint.question,
+            state,
+            cancel,
+        )
+    }
 
+    /// 基于已准备好系统消息的短期记忆运行一轮对话，结束后短期记忆中保留本轮的全部消息
+    pub(crate) fn run<M>(
+        &self,
+        planning: Planning,
+        mut short_memory: M,
+        question: &str,
+        mut state: RunState,
+        cancel: CancellationToken,
+    ) -> Result<AgentEventStream>
+    where
+        M: DerefMut<Target = ShortMemory> + Send + 'static,
+    {
+        let agent = self.clone();
         let user_message = planning.build_user_message(question)?;
+        let question = question.to_string();
 
+        // 在事件流的最后附上结束原因，设置了运行记录时保存本次运行
         let stream = stream! {
-            // 并不将第一条用户信息发送给大模型，只是用来反馈给客户端
-            // 用户提出的问题已经存入系统消息，作为Agent的任务目标
-            yield Ok(user_message.clone().into());
-
-            'outer: for _step in 1..=self.config.max_steps {
-                println!("history: {:#?}", short_memory.messages());
-
-                // 请求大模型
-                let response =
-                    match planning.execute(&self.client, &self.config.model, self.config.temperature, short_memory.messages()).await {
-                        Ok(response) => response,
-                        Err(e) => {
-                            println!("请求大模型遇到网络错误，马上进行重试操作... {:?}", e);
-                            continue;
-                        },
-                    };
+            let mut recorder = agent.history.as_ref().map(|_| RunRecorder::new(&question, &agent.config));
+            let mut status = None;
 
-                println!("response: {:#?}", response);
+            {
+                let mut events = Box::pin(agent.steps(&planning, &mut short_memory, &question, user_message, &mut state, cancel));
 
-                let response_message = response.choices.first().unwrap().message.clone();
+                while let Some(event) = events.next().await {
+                    status = status.or(RunStatus::from_event(&event));
+                    if let Some(recorder) = &mut recorder {
+                        recorder.observe(&event);
+                    }
+                    yield event;
+                }
+            }
 
-                if let Some(tool_calls) = response_message.tool_calls {
-                    // 构建调用工具的助手消息，放入短期记忆
-                    let assistant_message = ChatCompletionRequestAssistantMessageArgs::default()
-                        .tool_calls(tool_calls.clone())
-                        .build()?;
+            let status = status.unwrap_or(RunStatus::Error);
+            if let (Some(history), Some(recorder)) = (&agent.history, recorder) {
+                // 短期记忆可能已被截断或总结，保存本轮运行追加的全部消息和累计用量
+                let record = recorder.finish(status, short_memory.take_transcript(), state.usage);
+                if let Err(e) = history.insert(&record) {
+                    yield Err(e.context("保存运行记录失败"));
+                }
+            }
 
-                    short_memory.append(assistant_message.into());
+            yield Ok(AgentEvent::RunFinished { status });
+        };
 
-                    // tool_calls 工具调用
-                    for tool_call in tool_calls {
-                        match Tools::try_from(tool_call.function.clone()) {
-                            Ok(tool) => {
-                                let result = tool.execute().await?;
+        Ok(Box::pin(stream))
+    }
 
-                                // 将调用结果构建成工具消息，放入短期记忆
-                                let tool_message = ChatCompletionRequestToolMessageArgs::default()
-                                    .tool_call_id(tool_call.id)
-                                    .content(result)
-                                    .build()?;
+    /// 运行的主循环，产生除结束事件以外的全部事件
+    fn steps<'a>(
+        &'a self,
+        planning: &'a Planning,
+        short_memory: &'a mut ShortMemory,
+        question: &'a str,
+        user_message: ChatCompletionRequestUserMessage,
+        state: &'a mut RunState,
+        cancel: CancellationToken,
+    ) -> impl Stream<Item = Result<AgentEvent>> + Send + 'a {
+        let agent = self;
+        let language = self.config.language.to_string();
+        let question = question.to_string();
+
+        stream! {
+            if state.resumed {
+                yield Ok(AgentEvent::Resumed { step: state.step });
+            } else {
+                // 用户提出的问题已经存入系统消息，作为Agent的任务目标
+                // 第一轮对话并不将用户信息发送给大模型，只是用来反馈给客户端
+                yield Ok(AgentEvent::UserMessage { content: question.clone() });
+            }
 
-                                short_memory.append(tool_message.clone().into());
+            let max_steps = agent.config.max_steps;
+            let mut tool_failures = state.tool_failures;
+            let mut pending_tool_calls = std::mem::take(&mut state.pending_tool_calls);
+            let mut current_step = state.step;
 
-                                yield Ok(tool_message.into());
+            // 达到最大调用轮数或预算即将用尽时，得到收尾的原因
+            let reason = 'steps: {
+                for step in state.step..=max_steps {
+                    current_step = step;
+                    if cancel.is_cancelled() {
+                        yield agent.cancelled(planning, short_memory, step);
+                        return;
+                    }
+
+                    yield Ok(AgentEvent::StepStarted { step, max_steps });
+
+                    // 检查点中尚未执行的工具调用直接执行，不再请求大模型
+                    let (content, tool_calls, step_usage) = if !pending_tool_calls.is_empty() {
+                        (None, Some(std::mem::take(&mut pending_tool_calls)), RunUsage::default())
+                    } else {
+                        // 超出上下文上限时，由大模型总结最早的对话，总结失败时直接丢弃
+                        if let Some(target) = agent.summarize_target(short_memory) {
+                            let evicted = short_memory.evict(target);
+                            if !evicted.is_empty() {
+                                let request = planning.summarize(agent.backend.as_ref(), &agent.config.model, &language, &evicted);
+                                let Some(response) = until_cancelled(&cancel, request).await else {
+                                    yield agent.cancelled(planning, short_memory, step);
+                                    return;
+                                };
 
-                                // 如果工具是结束工具，则结束对话
-                                if let Tools::Finish(_) = tool {
-                                    break 'outer;
+                                let summary = match response {
+                                    Ok(response) => {
+                                        state.usage += RunUsage::new(response.usage.as_ref(), agent.config.prices.get(&agent.config.model));
+                                        response.choices.first()
+                                            .and_then(|choice| choice.message.content.clone())
+                                            .filter(|content| !content.is_empty())
+                                    },
+                                    Err(_) => None,
+                                };
+
+                                if let Some(summary) = &summary {
+                                    short_memory.prepend(planning.build_summary_message(summary)?.into());
+                                }
+                                yield Ok(AgentEvent::MemoryCompacted { evicted: evicted.len(), summary });
+                            }
+                        }
+
+                        // 请求大模型，失败时按重试策略在本轮内重试
+                        let mut attempt = 0;
+                        let (response_message, response_usage) = loop {
+                            attempt += 1;
+
+                            let response = if agent.config.stream {
+                                // 流式请求：逐个转发文本片段，同时拼装出完整回复
+                                let request = planning.execute_stream(agent.backend.as_ref(), &agent.config.model, agent.config.temperature, short_memory.messages(), &agent.tools);
+                                let Some(response) = until_cancelled(&cancel, request).await else {
+                                    yield agent.cancelled(planning, short_memory, step);
+                                    return;
+                                };
+
+                                match response {
+                                    Ok(mut chunks) => {
+                                        let mut assembler = ChatStreamAssembler::new();
+                                        let mut error = None;
+
+                                        loop {
+                                            let Some(chunk) = until_cancelled(&cancel, chunks.next()).await else {
+                                                yield agent.cancelled(planning, short_memory, step);
+                                                return;
+                                            };
+                                            let Some(chunk) = chunk else {
+                                                break;
+                                            };
+
+                                            match chunk {
+                                                Ok(chunk) => {
+                                                    if let Some(delta) = assembler.push(chunk) {
+                                                        yield Ok(AgentEvent::ContentDelta { step, delta });
+                                                    }
+                                                },
+                                                Err(e) => {
+                                                    error = Some(e);
+                                                    break;
+                                                },
+                                            }
+                                        }
+
+                                        match error {
+                                            Some(e) => Err(e),
+                                            None => Ok(assembler.finish()),
+                                        }
+                                    },
+                                    Err(e) => Err(e),
+                                }
+                            } else {
+                                let request = planning.execute(agent.backend.as_ref(), &agent.config.model, agent.config.temperature, short_memory.messages(), &agent.tools);
+                                let Some(response) = until_cancelled(&cancel, request).await else {
+                                    yield agent.cancelled(planning, short_memory, step);
+                                    return;
+                                };
+                                response
+                            };
+
+                            let e = match response {
+                                Ok(response) => match response.choices.into_iter().next() {
+                                    Some(choice) => break (choice.message, response.usage),
+                                    // 部分兼容接口偶尔返回空的 choices，按临时错误重试
+                                    None => anyhow::Error::from(BackendError::Transient("model returned no choices".to_string())),
+                                },
+                                Err(e) => e,
+                            };
+
+                            let class = classify(&e);
+                            match agent.config.retry_policy.delay(attempt, class) {
+                                Some(delay) => {
+                                    yield Ok(AgentEvent::Retrying {
+                                        step,
+                                        attempt,
+                                        delay_ms: delay.as_millis() as u64,
+                                        error: e.to_string(),
+                                    });
+                                    if until_cancelled(&cancel, tokio::time::sleep(delay)).await.is_none() {
+                                        yield agent.cancelled(planning, short_memory, step);
+                                        return;
+                                    }
+                                },
+                                None => {
+                                    let reason = match class {
+                                        ErrorClass::Fatal => "不可重试的错误".to_string(),
+                                        ErrorClass::Transient { .. } => format!("已重试 {} 次", attempt - 1),
+                                    };
+                                    yield Err(e.context(format!("请求大模型失败（{}）", reason)));
+                                    return;
+                                },
+                            }
+                        };
+
+                        let mut step_usage = RunUsage::new(response_usage.as_ref(), agent.config.prices.get(&agent.config.model));
+                        state.usage += step_usage;
+
+                        yield Ok(AgentEvent::ModelResponded {
+                            content: response_message.content.clone(),
+                            tool_calls: response_message.tool_calls.clone().unwrap_or_default(),
+                            usage: response_usage,
+                        });
+
+                        let (content, tool_calls) = match agent.config.tool_mode {
+                            ToolMode::Native => (response_message.content, response_message.tool_calls),
+                            ToolMode::Prompted => {
+                                // 提示词模式：回复原样放入短期记忆，从中解析出的命令作为工具调用执行
+                                let content = response_message.content.unwrap_or_default();
+                                short_memory.append(planning.build_assistant_message(&content)?.into());
+
+                                let mut parsed = planning.parse_response(&content);
+                                if parsed.is_err() {
+                                    // 修复 JSON 格式失败时，请求大模型将回复改写为 JSON
+                                    let request = planning.fix_response(agent.backend.as_ref(), &agent.config.model, &content);
+                                    let Some(response) = until_cancelled(&cancel, request).await else {
+                                        yield agent.cancelled(planning, short_memory, step);
+                                        return;
+                                    };
+
+                                    if let Ok(response) = response {
+                                        let fix_usage = RunUsage::new(response.usage.as_ref(), agent.config.prices.get(&agent.config.model));
+                                        state.usage += fix_usage;
+                                        step_usage += fix_usage;
+
+                                        let fixed = response.choices.first().and_then(|choice| choice.message.content.clone());
+                                        yield Ok(AgentEvent::ModelResponded { content: fixed.clone(), tool_calls: Vec::new(), usage: response.usage.clone() });
+
+                                        if let Some(Ok(response)) = fixed.map(|fixed| planning.parse_response(&fixed)) {
+                                            parsed = Ok(response);
+                                        }
+                                    }
+                                }
+
+                                match parsed {
+                                    Ok(response) => (None, Some(vec![command_tool_call(format!("command_{}", step), response.command)])),
+                                    Err(e) => {
+                                        let error = e.to_string();
+                                        short_memory.append(planning.build_invalid_response_message(&error)?.into());
+                                        yield Ok(AgentEvent::InvalidResponse { step, error: error.clone() });
+
+                                        tool_failures += 1;
+                                        if tool_failures > agent.config.max_tool_failures {
+                                            yield Err(anyhow!("工具调用失败次数超过上限 {}，最后一次错误: {}", agent.config.max_tool_failures, error));
+                                            return;
+                                        }
+                                        (None, None)
+                                    },
                                 }
                             },
-                            Err(e) => println!("工具序列化失败: \n{:?} \n{:?}", tool_call.function, e),
                         };
+
+                        // 结束工具之后的调用不会执行，不放入短期记忆，避免留下没有结果的 tool_call
+                        let tool_calls = tool_calls.map(until_finish);
+
+                        if let Some(tool_calls) = &tool_calls {
+                            // 构建调用工具的助手消息，放入短期记忆，提示词模式下回复已经放入
+                            if agent.config.tool_mode == ToolMode::Native {
+                                let assistant_message = ChatCompletionRequestAssistantMessageArgs::default()
+                                    .tool_calls(tool_calls.clone())
+                                    .build()?;
+
+                                short_memory.append(assistant_message.into());
+                            }
+
+                            // 工具执行前保存检查点，恢复时重新执行这些工具调用
+                            agent.save_checkpoint(&question, short_memory, step, tool_failures, state.usage, tool_calls)?;
+                        }
+
+                        (content, tool_calls, step_usage)
+                    };
+
+                    if let Some(tool_calls) = tool_calls {
+                        // tool_calls 工具调用
+                        let mut calls = Vec::new();
+                        for tool_call in tool_calls {
+                            yield Ok(AgentEvent::ToolCallStarted {
+                                id: tool_call.id.clone(),
+                                name: tool_call.function.name.clone(),
+                                arguments: tool_call.function.arguments.clone(),
+                            });
+
+                            calls.push(ToolCall {
+                                id: tool_call.id,
+                                name: tool_call.function.name.clone(),
+                                timeout: agent.config.tool_timeout_for(&tool_call.function.name),
+                                tool: agent.tools.prepare(tool_call.function),
+                            });
+                        }
+
+                        // 并发执行工具调用，结果按调用顺序返回
+                        let mut outcomes = Box::pin(run_tool_calls(calls, agent.config.max_concurrent_tools));
+
+                        loop {
+                            let Some(outcome) = until_cancelled(&cancel, outcomes.next()).await else {
+                                yield agent.cancelled(planning, short_memory, step);
+                                return;
+                            };
+                            let Some(ToolOutcome { id, name, result }) = outcome else {
+                                break;
+                            };
+
+                            // 解析失败或执行失败时，将错误信息作为工具消息返回给大模型，
+                            // 保证每个 tool_call 都有对应的工具消息，大模型可以据此修正
+                            let content = match &result {
+                                Ok(output) => output.content.clone(),
+                                Err(e) => e.to_content(),
+                            };
+
+                            agent.append_tool_result(planning, short_memory, &id, &name, content)?;
+
+                            match result {
+                                Ok(ToolOutput { content: result, sources }) => {
+                                    let is_finish = name == "finish";
+                                    yield Ok(AgentEvent::ToolCallFinished { id, name, result: result.clone(), sources });
+
+                                    // 如果工具是结束工具，则结束对话
+                                    if is_finish {
+                                        agent.remove_checkpoint()?;
+                                        yield Ok(AgentEvent::FinalAnswer { answer: result.clone() });
+
+                                        if let Err(e) = agent.remember_outcome(&question, &result).await {
+                                            yield Err(e.context("保存长期记忆失败"));
+                                        }
+                                        return;
+                                    }
+                                },
+                                Err(e) => {
+                                    yield Ok(AgentEvent::ToolFailed { id, name, kind: e.kind, error: e.message.clone() });
+
+                                    tool_failures += 1;
+                                    if tool_failures > agent.config.max_tool_failures {
+                                        yield Err(anyhow!("工具调用失败次数超过上限 {}，最后一次错误: {}", agent.config.max_tool_failures, e));
+                                        return;
+                                    }
+                                },
+                            }
+                        }
                     }
+
+                    if let Some(assistant_prompt) = content {
+                        if assistant_prompt.is_empty() {
+                            // 如果助手提示为空，继续使用用户信息
+                            short_memory.append(user_message.clone().into());
+                            yield Ok(AgentEvent::UserMessage { content: question.clone() });
+                        } else {
+                            // 构建助手提示，放入短期记忆，在下次对话中使用
+                            let assistant_message = planning.build_assistant_message(&assistant_prompt)?;
+                            short_memory.append(assistant_message.into());
+                        }
+                    }
+
+                    // 预计下一轮会超出预算时，要求大模型立即给出最终答案
+                    if let Some(reason) = agent.config.budget.exceeded_by(&state.usage, &step_usage) {
+                        yield Ok(AgentEvent::BudgetExceeded { reason: reason.clone(), usage: state.usage });
+                        break 'steps reason;
+                    }
+
+                    agent.save_checkpoint(&question, short_memory, step + 1, tool_failures, state.usage, &[])?;
                 }
 
-                if let Some(assistant_prompt) = response_message.content {
-                    if assistant_prompt.is_empty() {
-                        // 如果助手提示为空，继续使用用户信息
-                        short_memory.append(user_message.clone().into());
-                        yield Ok(user_message.clone().into());
-                    } else {
-                        // 构建助手提示，放入短期记忆，在下次对话中使用
-                        let assistant_message = planning.build_assistant_message(&assistant_prompt)?;
-                        short_memory.append(assistant_message.clone().into());
-                        yield Ok(assistant_message.into());
+                yield Ok(AgentEvent::MaxStepsReached { max_steps });
+                format!("已达到最大调用轮数 {}，任务尚未完成。", max_steps)
+            };
+
+            // 收尾：根据已收集的信息尽可能给出最终答案
+            let Some(result) = until_cancelled(&cancel, agent.wrap_up(planning, short_memory, &reason)).await else {
+                yield agent.cancelled(planning, short_memory, current_step);
+                return;
+            };
+            let (response, answer) = result.context("收尾请求大模型失败")?;
+            state.usage += RunUsage::new(response.usage.as_ref(), agent.config.prices.get(&agent.config.model));
+            let response_message = response.choices.first().map(|choice| choice.message.clone());
+
+            yield Ok(AgentEvent::ModelResponded {
+                content: response_message.as_ref().and_then(|message| message.content.clone()),
+                tool_calls: response_message.and_then(|message| message.tool_calls).unwrap_or_default(),
+                usage: response.usage.clone(),
+            });
+
+            agent.remove_checkpoint()?;
+            if let Some(answer) = answer {
+                yield Ok(AgentEvent::FinalAnswer { answer });
+            }
+        }
+    }
+
+    /// 按配置的上下文上限创建短期记忆，总结策略下由 Agent 在请求前处理超出的部分
+    pub(crate) fn short_memory(&self) -> ShortMemory {
+        let max_tokens = match self.config.memory_strategy {
+            MemoryStrategy::Truncate => self.config.context_window,
+            MemoryStrategy::Summarize => None,
+        };
+
+        ShortMemory::with_budget(self.tokenizer.clone(), max_tokens)
+    }
+
+    /// 需要总结时返回移除较早对话后的目标 token 数，留出一半空间避免每轮都要总结
+    fn summarize_target(&self, short_memory: &ShortMemory) -> Option<usize> {
+        match (self.config.memory_strategy, self.config.context_window) {
+            (MemoryStrategy::Summarize, Some(max_tokens)) if short_memory.tokens() > max_tokens => {
+                Some(max_tokens / 2)
+            }
+            _ => None,
+        }
+    }
+
+    /// 要求大模型停止调用工具，根据已有信息给出最终答案，返回大模型的回复和最终答案
+    async fn wrap_up(
+        &self,
+        planning: &Planning,
+        short_memory: &mut ShortMemory,
+        reason: &str,
+    ) -> Result<(CreateChatCompletionResponse, Option<String>)> {
+        short_memory.append(planning.build_wrap_up_message(reason)?.into());
+
+        let response = planning
+            .execute(
+                self.backend.as_ref(),
+                &self.config.model,
+                self.config.temperature,
+                short_memory.messages(),
+                &self.tools,
+            )
+            .await?;
+
+        let Some(message) = response
+            .choices
+            .first()
+            .map(|choice| choice.message.clone())
+        else {
+            return Ok((response, None));
+        };
+
+        let (tool_calls, content) = match self.config.tool_mode {
+            ToolMode::Native => (message.tool_calls.unwrap_or_default(), message.content),
+            ToolMode::Prompted => {
+                let content = message.content.unwrap_or_default();
+                short_memory.append(planning.build_assistant_message(&content)?.into());
+
+                // 回复无法解析时将回复内容作为答案
+                match planning.parse_response(&content) {
+                    Ok(response) => (
+                        vec![command_tool_call(
+                            "command_wrap_up".to_string(),
+                            response.command,
+                        )],
+                        Some(response.thoughts.speak),
+                    ),
+                    Err(_) => (Vec::new(), Some(content)),
+                }
+            }
+        };
+
+        let finish = tool_calls
+            .into_iter()
+            .find(|tool_call| tool_call.function.name == "finish");
+
+        // 优先使用结束工具给出的答案，大模型没有调用结束工具时使用回复内容
+        let answer = match finish {
+            Some(tool_call) => {
+                if self.config.tool_mode == ToolMode::Native {
+                    let assistant_message = ChatCompletionRequestAssistantMessageArgs::default()
+                        .tool_calls(vec![tool_call.clone()])
+                        .build()?;
+                    short_memory.append(assistant_message.into());
+                }
+
+                let id = tool_call.id.clone();
+                let result = match self.tools.prepare(tool_call.function) {
+                    Ok((tool, arguments)) => tool.execute(arguments).await.map_err(|e| {
+                        e.downcast::<ToolError>()
+                            .unwrap_or_else(|e| ToolError::execution_failed("finish", e))
+                    }),
+                    Err(e) => Err(e),
+                };
+
+                let content = match &result {
+                    Ok(result) => result.clone(),
+                    Err(e) => e.to_content(),
+                };
+                self.append_tool_result(planning, short_memory, &id, "finish", content)?;
+
+                result.ok()
+            }
+            None => {
+                // 回复内容作为答案，放入短期记忆供会话的下一轮使用，提示词模式下回复已经放入
+                if let Some(content) = content.as_ref().filter(|content| !content.is_empty()) {
+                    if self.config.tool_mode == ToolMode::Native {
+                        short_memory.append(planning.build_assistant_message(content)?.into());
                     }
                 }
+                None
             }
         };
 
-        Ok(Box::pin(stream))
+        let answer = answer.or(content.filter(|content| !content.is_empty()));
+
+        Ok((response, answer))
+    }
+
+    /// 取消运行，为尚未返回结果的工具调用补上取消的工具消息，
+    /// 保证短期记忆中的对话在会话的下一轮仍然可以发送给大模型
+    fn cancelled(
+        &self,
+        planning: &Planning,
+        short_memory: &mut ShortMemory,
+        step: usize,
+    ) -> Result<AgentEvent> {
+        // 最后一条助手消息中还没有工具消息的调用
+        let mut unanswered: Vec<(String, String)> = Vec::new();
+        for message in short_memory.messages() {
+            match message {
+                ChatCompletionRequestMessage::Assistant(message) => {
+                    unanswered = message
+                        .tool_calls
+                        .into_iter()
+                        .flatten()
+                        .map(|tool_call| (tool_call.id, tool_call.function.name))
+                        .collect();
+                }
+                ChatCompletionRequestMessage::Tool(message) => {
+                    unanswered.retain(|(id, _)| *id != message.tool_call_id);
+                }
+                _ => {}
+            }
+        }
+
+        for (id, name) in unanswered {
+            let content = ToolError::cancelled(name.as_str()).to_content();
+            self.append_tool_result(planning, short_memory, &id, &name, content)?;
+        }
+
+        Ok(AgentEvent::Cancelled {
+            step,
+            transcript: short_memory.messages(),
+        })
+    }
+
+    /// 将工具调用的结果放入短期记忆，提示词模式下作为用户消息
+    fn append_tool_result(
+        &self,
+        planning: &Planning,
+        short_memory: &mut ShortMemory,
+        id: &str,
+        name: &str,
+        content: String,
+    ) -> Result<()> {
+        let message = match self.config.tool_mode {
+            ToolMode::Native => ChatCompletionRequestToolMessageArgs::default()
+                .tool_call_id(id)
+                .content(content)
+                .build()?
+                .into(),
+            ToolMode::Prompted => planning.build_command_result(name, &content)?.into(),
+        };
+        short_memory.append(message);
+
+        Ok(())
+    }
+
+    /// 未设置检查点路径时不保存
+    fn save_checkpoint(
+        &self,
+        question: &str,
+        short_memory: &ShortMemory,
+        step: usize,
+        tool_failures: usize,
+        usage: RunUsage,
+        pending_tool_calls: &[ChatCompletionMessageToolCall],
+    ) -> Result<()> {
+        let Some(path) = &self.config.checkpoint_path else {
+            return Ok(());
+        };
+
+        let checkpoint = Checkpoint {
+            config: self.config.clone(),
+            question: question.to_string(),
+            messages: short_memory.messages(),
+            step,
+            tool_failures,
+            usage,
+            pending_tool_calls: pending_tool_calls.to_vec(),
+        };
+
+        checkpoint.save(path)
+    }
+
+    /// 从长期记忆中检索与问题相关的记忆，未设置长期记忆时返回空
+    pub(crate) async fn recall(&self, question: &str) -> Result<Vec<String>> {
+        let Some(long_memory) = &self.long_memory else {
+            return Ok(Vec::new());
+        };
+
+        let memories = long_memory
+            .search(question, self.config.long_memory_top_k)
+            .await
+            .context("检索长期记忆失败")?;
+
+        Ok(memories
+            .into_iter()
+            .map(|(entry, _)| entry.content)
+            .collect())
+    }
+
+    /// 将完成的任务写入长期记忆，供之后的任务参考
+    async fn remember_outcome(&self, question: &str, answer: &str) -> Result<()> {
+        let Some(long_memory) = &self.long_memory else {
+            return Ok(());
+        };
+
+        let content = format!("问题: {}\n答案: {}", question, answer);
+        long_memory.add(MemoryKind::TaskOutcome, &content).await?;
+
+        Ok(())
+    }
+
+    /// 运行完成后删除检查点，避免重复恢复已完成的任务
+    fn remove_checkpoint(&self) -> Result<()> {
+        match &self.config.checkpoint_path {
+            Some(path) if path.exists() => Ok(std::fs::remove_file(path)?),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 一次运行的起始状态
+pub(crate) struct RunState {
+    step: usize,
+    tool_failures: usize,
+    usage: RunUsage,
+    pending_tool_calls: Vec<ChatCompletionMessageToolCall>,
+    resumed: bool,
+}
+
+impl RunState {
+    pub(crate) fn new() -> Self {
+        Self {
+            step: 1,
+            tool_failures: 0,
+            usage: RunUsage::default(),
+            pending_tool_calls: Vec::new(),
+            resumed: false,
+        }
+    }
+}
+
+/// 提示词模式下由回复中的命令生成的工具调用
+fn command_tool_call(id: String, command: Command) -> ChatCompletionMessageToolCall {
+    ChatCompletionMessageToolCall {
+        id,
+        r#type: ChatCompletionToolType::Function,
+        function: FunctionCall {
+            name: command.name,
+            arguments: command.args.to_string(),
+        },
+    }
+}
+
+/// 截取到第一个结束工具调用为止，之后的调用不会执行
+fn until_finish(
+    mut tool_calls: Vec<ChatCompletionMessageToolCall>,
+) -> Vec<ChatCompletionMessageToolCall> {
+    if let Some(position) = tool_calls
+        .iter()
+        .position(|tool_call| tool_call.function.name == "finish")
+    {
+        tool_calls.truncate(position + 1);
+    }
+
+    tool_calls
+}
+
+/// 等待 future 完成，取消时丢弃 future 并返回 None
+async fn until_cancelled<F: Future>(cancel: &CancellationToken, future: F) -> Option<F::Output> {
+    tokio::select! {
+        biased;
+        _ = cancel.cancelled() => None,
+        output = future => Some(output),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{
+        agent::{Budget, ModelPrice},
+        llm::{response_into_chunks, BackendError, MockBackend, RetryPolicy},
+        tools::{ToolError, ToolErrorKind},
+    };
+    use async_openai::types::{
+        ChatCompletionMessageToolCall, ChatCompletionRequestAssistantMessage,
+        ChatCompletionRequestMessage, ChatCompletionRequestUserMessageArgs,
+        ChatCompletionRequestUserMessageContent, ChatCompletionToolType, CompletionUsage,
+        FunctionCall,
+    };
+    use std::time::Duration;
+
+    fn mock_config(max_steps: usize) -> anyhow::Result<ReActAgentConfig> {
+        let config = ReActAgentConfig::builder()
+            .set_api_key("my_api_key")
+            .set_model("mock-model")
+            .try_set_base_url("http://localhost")?
+            .set_max_steps(max_steps)
+            .set_retry_policy(RetryPolicy {
+                initial_backoff: Duration::ZERO,
+                ..Default::default()
+            })
+            .build()?;
+
+        Ok(config)
+    }
+
+    fn tool_call(id: &str, name: &str, arguments: &str) -> ChatCompletionMessageToolCall {
+        ChatCompletionMessageToolCall {
+            id: id.to_string(),
+            r#type: ChatCompletionToolType::Function,
+            function: FunctionCall {
+                name: name.to_string(),
+                arguments: arguments.to_string(),
+            },
+        }
+    }
+
+    fn tool_message(id: &str, content: &str) -> anyhow::Result<ChatCompletionRequestMessage> {
+        Ok(ChatCompletionRequestToolMessageArgs::default()
+            .tool_call_id(id)
+            .content(content)
+            .build()?
+            .into())
+    }
+
+    fn user(content: &str) -> AgentEvent {
+        AgentEvent::UserMessage {
+            content: content.to_string(),
+        }
+    }
+
+    fn step(step: usize, max_steps: usize) -> AgentEvent {
+        AgentEvent::StepStarted { step, max_steps }
+    }
+
+    fn responded(
+        content: Option<&str>,
+        tool_calls: Vec<ChatCompletionMessageToolCall>,
+    ) -> AgentEvent {
+        AgentEvent::ModelResponded {
+            content: content.map(ToString::to_string),
+            tool_calls,
+            usage: None,
+        }
+    }
+
+    // 事件流以 RunFinished 结束，返回其余事件和结束原因
+    async fn collect_results(
+        stream: AgentEventStream,
+    ) -> (Vec<anyhow::Result<AgentEvent>>, RunStatus) {
+        let mut results = stream.collect::<Vec<_>>().await;
+        match results.pop() {
+            Some(Ok(AgentEvent::RunFinished { status })) => (results, status),
+            event => panic!("expected run finished, got {:?}", event),
+        }
+    }
+
+    async fn collect_events(
+        agent: ReActAgent<MockBackend>,
+        question: &str,
+    ) -> anyhow::Result<(Vec<AgentEvent>, RunStatus)> {
+        let (results, status) = collect_results(agent.invoke(question).await?).await;
+        let events = results.into_iter().collect::<anyhow::Result<_>>()?;
+        Ok((events, status))
+    }
 
     #[test]
     fn test_react_agent() -> anyhow::Result<()> {
@@ -137,4 +965,1193 @@ mod tests {
         assert_eq!(agent.config, config);
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_react_agent_finish_ends_run() -> anyhow::Result<()> {
+        let backend = MockBackend::with_responses([
+            MockBackend::text_response("我需要先思考一下"),
+            MockBackend::tool_calls_response([("call_1", "finish", r#"{"result":"42"}"#)]),
+            MockBackend::text_response("不应被请求"),
+        ]);
+
+        let agent = ReActAgent::with_backend(mock_config(10)?, backend.clone());
+        let (events, status) = collect_events(agent, "问题").await?;
+        assert_eq!(status, RunStatus::Completed);
+
+        assert_eq!(
+            events,
+            vec![
+                user("问题"),
+                step(1, 10),
+                AgentEvent::ContentDelta {
+                    step: 1,
+                    delta: "我需要先思考一下".to_string(),
+                },
+                responded(Some("我需要先思考一下"), vec![]),
+                step(2, 10),
+                responded(
+                    None,
+                    vec![tool_call("call_1", "finish", r#"{"result":"42"}"#)]
+                ),
+                AgentEvent::ToolCallStarted {
+                    id: "call_1".to_string(),
+                    name: "finish".to_string(),
+                    arguments: r#"{"result":"42"}"#.to_string(),
+                },
+                AgentEvent::ToolCallFinished {
+                    id: "call_1".to_string(),
+                    name: "finish".to_string(),
+                    result: "42".to_string(),
+                    sources: Vec::new(),
+                },
+                AgentEvent::FinalAnswer {
+                    answer: "42".to_string(),
+                },
+            ]
+        );
+
+        // 结束工具之后不再请求大模型
+        let requests = backend.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(backend.remaining(), 1);
+        assert_eq!(requests[0].model, "mock-model");
+
+        // 第一条请求只包含系统消息，用户问题已写入系统消息
+        assert_eq!(requests[0].messages.len(), 1);
+        assert!(matches!(
+            requests[0].messages[0],
+            ChatCompletionRequestMessage::System(_)
+        ));
+
+        let assistant: ChatCompletionRequestMessage =
+            ChatCompletionRequestAssistantMessageArgs::default()
+                .content("我需要先思考一下")
+                .build()?
+                .into();
+        assert_eq!(requests[1].messages[1], assistant);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_react_agent_reports_unknown_tool() -> anyhow::Result<()> {
+        let backend = MockBackend::with_responses([
+            MockBackend::tool_calls_response([("call_1", "unknown_tool", "{}")]),
+            MockBackend::tool_calls_response([("call_2", "finish", r#"{"result":"done"}"#)]),
+        ]);
+
+        let agent = ReActAgent::with_backend(mock_config(10)?, backend.clone());
+        let (events, status) = collect_events(agent, "问题").await?;
+        assert_eq!(status, RunStatus::Completed);
+
+        assert!(events.contains(&AgentEvent::ToolFailed {
+            id: "call_1".to_string(),
+            name: "unknown_tool".to_string(),
+            kind: ToolErrorKind::UnknownTool,
+            error: "Unknown tool: unknown_tool".to_string(),
+        }));
+        assert_eq!(
+            events.last(),
+            Some(&AgentEvent::FinalAnswer {
+                answer: "done".to_string()
+            })
+        );
+
+        let requests = backend.requests();
+        assert_eq!(requests.len(), 2);
+
+        let assistant =
+            ChatCompletionRequestMessage::Assistant(ChatCompletionRequestAssistantMessage {
+                tool_calls: Some(vec![tool_call("call_1", "unknown_tool", "{}")]),
+                ..Default::default()
+            });
+        assert_eq!(requests[1].messages[1], assistant);
+
+        // 失败的工具调用同样有对应的工具消息，错误信息返回给大模型
+        assert_eq!(
+            requests[1].messages[2],
+            tool_message(
+                "call_1",
+                &ToolError::unknown_tool("unknown_tool").to_content()
+            )?
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_react_agent_feeds_tool_errors_back() -> anyhow::Result<()> {
+        let backend = MockBackend::with_responses([
+            MockBackend::tool_calls_response([
+                (
+                    "call_1",
+                    "file_write",
+                    r#"{"filename":"missing/dir/test.txt","content":"x"}"#,
+                ),
+                ("call_2", "finish", r#"{"answer":"wrong field"}"#),
+            ]),
+            MockBackend::tool_calls_response([("call_3", "finish", r#"{"result":"done"}"#)]),
+        ]);
+
+        let agent = ReActAgent::with_backend(mock_config(10)?, backend.clone());
+        let (events, status) = collect_events(agent, "问题").await?;
+        assert_eq!(status, RunStatus::Completed);
+
+        let failures = events
+            .iter()
+            .filter_map(|event| match event {
+                AgentEvent::ToolFailed { id, kind, .. } => Some((id.as_str(), *kind)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            failures,
+            vec![
+                ("call_1", ToolErrorKind::ExecutionFailed),
+                ("call_2", ToolErrorKind::InvalidArguments),
+            ]
+        );
+
+        let messages = &backend.requests()[1].messages;
+        assert_eq!(messages.len(), 4);
+        for (message, kind) in messages[2..]
+            .iter()
+            .zip(["execution_failed", "invalid_arguments"])
+        {
+            let ChatCompletionRequestMessage::Tool(message) = message else {
+                panic!("expected tool message, got {:?}", message);
+            };
+            let content: serde_json::Value = serde_json::from_str(&message.content)?;
+            assert_eq!(content["error"]["kind"], kind);
+        }
+
+        assert_eq!(
+            events.last(),
+            Some(&AgentEvent::FinalAnswer {
+                answer: "done".to_string()
+            })
+        );
+
+        Ok(())
+    }
+
+    // 在内存中记录写入顺序的工具，不可并发执行
+    #[derive(Default)]
+    struct OrderedWrite {
+        writes: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+    }
+
+    impl crate::tools::Tool for OrderedWrite {
+        fn name(&self) -> &str {
+            "ordered_write"
+        }
+
+        fn description(&self) -> &str {
+            "等待 delay_ms 毫秒后写入内容"
+        }
+
+        fn parameters(&self) -> serde_json::Value {
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "content": { "type": "string" },
+                    "delay_ms": { "type": "integer" },
+                },
+                "required": ["content", "delay_ms"],
+            })
+        }
+
+        fn execute(
+            &self,
+            arguments: serde_json::Value,
+        ) -> futures::future::BoxFuture<'_, anyhow::Result<String>> {
+            Box::pin(async move {
+                let delay = arguments["delay_ms"].as_u64().unwrap_or_default();
+                tokio::time::sleep(Duration::from_millis(delay)).await;
+                let content = arguments["content"].as_str().unwrap_or_default();
+                self.writes.lock().unwrap().push(content.to_string());
+                Ok("写入成功".to_string())
+            })
+        }
+
+        fn is_concurrent_safe(&self) -> bool {
+            false
+        }
+    }
+
+    #[tokio::test]
+    async fn test_react_agent_runs_parallel_tool_calls_in_order() -> anyhow::Result<()> {
+        let backend = MockBackend::with_responses([
+            MockBackend::tool_calls_response([
+                ("call_1", "unknown_a", "{}"),
+                (
+                    "call_2",
+                    "ordered_write",
+                    r#"{"content":"first","delay_ms":50}"#,
+                ),
+                (
+                    "call_3",
+                    "ordered_write",
+                    r#"{"content":"second","delay_ms":0}"#,
+                ),
+                ("call_4", "unknown_b", "{}"),
+            ]),
+            MockBackend::tool_calls_response([
+                ("call_5", "finish", r#"{"result":"done"}"#),
+                ("call_6", "unknown_c", "{}"),
+            ]),
+        ]);
+
+        let tool = OrderedWrite::default();
+        let writes = tool.writes.clone();
+        let agent = ReActAgent::with_backend(mock_config(10)?, backend.clone())
+            .with_tools(ToolRegistry::builtin().with(tool));
+        let (events, status) = collect_events(agent, "问题").await?;
+        assert_eq!(status, RunStatus::Completed);
+
+        // 工具消息按调用顺序写入短期记忆
+        let tool_call_ids = backend.requests()[1].messages[2..]
+            .iter()
+            .map(|message| match message {
+                ChatCompletionRequestMessage::Tool(message) => message.tool_call_id.clone(),
+                _ => panic!("expected tool message, got {:?}", message),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(tool_call_ids, vec!["call_1", "call_2", "call_3", "call_4"]);
+
+        // 不可并发的调用按顺序执行，先调用的写入即使更慢也先完成
+        assert_eq!(*writes.lock().unwrap(), vec!["first", "second"]);
+
+        // 结束工具之后的调用不再执行
+        assert!(!events.iter().any(|event| matches!(
+            event,
+            AgentEvent::ToolCallStarted { id, .. } if id == "call_6"
+        )));
+        assert_eq!(
+            events.last(),
+            Some(&AgentEvent::FinalAnswer {
+                answer: "done".to_string()
+            })
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_react_agent_stops_when_tool_failure_budget_exhausted() -> anyhow::Result<()> {
+        let backend = MockBackend::with_responses([
+            MockBackend::tool_calls_response([("call_1", "unknown_tool", "{}")]),
+            MockBackend::tool_calls_response([("call_2", "unknown_tool", "{}")]),
+            MockBackend::tool_calls_response([("call_3", "finish", r#"{"result":"done"}"#)]),
+        ]);
+
+        let mut config = mock_config(10)?;
+        config.max_tool_failures = 1;
+
+        let agent = ReActAgent::with_backend(config, backend.clone());
+        let (results, status) = collect_results(agent.invoke("问题").await?).await;
+        assert_eq!(status, RunStatus::Error);
+
+        let error = results.last().unwrap().as_ref().unwrap_err();
+        assert!(error.to_string().contains("工具调用失败次数超过上限 1"));
+        assert_eq!(backend.requests().len(), 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_react_agent_empty_content_falls_back_to_user_message() -> anyhow::Result<()> {
+        let backend = MockBackend::with_responses([
+            MockBackend::text_response(""),
+            MockBackend::tool_calls_response([("call_1", "finish", r#"{"result":"done"}"#)]),
+        ]);
+
+        let agent = ReActAgent::with_backend(mock_config(10)?, backend.clone());
+        let (events, status) = collect_events(agent, "问题").await?;
+        assert_eq!(status, RunStatus::Completed);
+
+        assert_eq!(
+            events[..4],
+            [
+                user("问题"),
+                step(1, 10),
+                responded(Some(""), vec![]),
+                user("问题")
+            ]
+        );
+
+        let user_message: ChatCompletionRequestMessage =
+            ChatCompletionRequestUserMessageArgs::default()
+                .content("问题")
+                .build()?
+                .into();
+        assert_eq!(backend.requests()[1].messages[1], user_message);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_react_agent_stops_at_max_steps() -> anyhow::Result<()> {
+        let backend = MockBackend::with_responses([
+            MockBackend::text_response("第一步"),
+            MockBackend::text_response("第二步"),
+            MockBackend::text_response("第三步"),
+        ]);
+
+        let agent = ReActAgent::with_backend(mock_config(2)?, backend.clone());
+        let (events, status) = collect_events(agent, "问题").await?;
+        assert_eq!(status, RunStatus::StepLimit);
+
+        // 达到最大调用轮数后收尾，大模型没有调用结束工具时使用回复内容作为答案
+        assert!(events.contains(&AgentEvent::MaxStepsReached { max_steps: 2 }));
+        assert!(!events.contains(&step(3, 2)));
+        assert_eq!(
+            events.last(),
+            Some(&AgentEvent::FinalAnswer {
+                answer: "第三步".to_string()
+            })
+        );
+
+        let requests = backend.requests();
+        assert_eq!(requests.len(), 3);
+        match requests[2].messages.last() {
+            Some(ChatCompletionRequestMessage::User(message)) => match &message.content {
+                ChatCompletionRequestUserMessageContent::Text(text) => {
+                    assert!(text.starts_with("已达到最大调用轮数 2"));
+                }
+                content => panic!("unexpected content: {:?}", content),
+            },
+            message => panic!("expected user message, got {:?}", message),
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_react_agent_retries_transient_errors() -> anyhow::Result<()> {
+        let backend = MockBackend::new();
+        backend.push_error(BackendError::Transient("overloaded".to_string()));
+        backend.push_error(BackendError::RateLimited {
+            message: "slow down".to_string(),
+            retry_after: None,
+        });
+        backend.push_response(MockBackend::tool_calls_response([(
+            "call_1",
+            "finish",
+            r#"{"result":"done"}"#,
+        )]));
+
+        let mut config = mock_config(1)?;
+        config.retry_policy.max_attempts = 3;
+
+        let agent = ReActAgent::with_backend(config, backend.clone());
+        let (events, status) = collect_events(agent, "问题").await?;
+        assert_eq!(status, RunStatus::Completed);
+
+        // 重试不消耗调用轮数，max_steps 为 1 时仍能完成任务
+        let retries = events
+            .iter()
+            .filter_map(|event| match event {
+                AgentEvent::Retrying { step, attempt, .. } => Some((*step, *attempt)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(retries, vec![(1, 1), (1, 2)]);
+        assert_eq!(
+            events.last(),
+            Some(&AgentEvent::FinalAnswer {
+                answer: "done".to_string()
+            })
+        );
+        assert_eq!(backend.requests().len(), 3);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_react_agent_fails_fast_on_fatal_error() -> anyhow::Result<()> {
+        let backend = MockBackend::new();
+        backend.push_error(BackendError::Fatal("invalid api key".to_string()));
+
+        let agent = ReActAgent::with_backend(mock_config(10)?, backend.clone());
+        let (results, status) = collect_results(agent.invoke("问题").await?).await;
+        assert_eq!(status, RunStatus::Error);
+
+        let error = results.last().unwrap().as_ref().unwrap_err();
+        assert!(error.to_string().contains("不可重试"));
+        assert!(!results
+            .iter()
+            .any(|event| matches!(event, Ok(AgentEvent::Retrying { .. }))));
+        assert_eq!(backend.requests().len(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_react_agent_retries_empty_choices() -> anyhow::Result<()> {
+        let mut empty = MockBackend::text_response("");
+        empty.choices.clear();
+
+        let backend = MockBackend::with_responses([
+            empty.clone(),
+            MockBackend::tool_calls_response([("call_1", "finish", r#"{"result":"done"}"#)]),
+        ]);
+
+        let agent = ReActAgent::with_backend(mock_config(10)?, backend.clone());
+        let (events, status) = collect_events(agent, "问题").await?;
+        assert_eq!(status, RunStatus::Completed);
+        assert!(events.iter().any(|event| matches!(
+            event,
+            AgentEvent::Retrying { error, .. } if error == "transient error: model returned no choices"
+        )));
+        assert_eq!(backend.requests().len(), 2);
+
+        // 重试用尽时以错误结束，而不是 panic
+        let mut config = mock_config(10)?;
+        config.retry_policy.max_attempts = 1;
+        let backend = MockBackend::with_responses([empty]);
+        let agent = ReActAgent::with_backend(config, backend);
+        let (results, status) = collect_results(agent.invoke("问题").await?).await;
+        assert_eq!(status, RunStatus::Error);
+        let error = results.last().unwrap().as_ref().unwrap_err();
+        assert!(format!("{:#}", error).contains("model returned no choices"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_react_agent_surfaces_error_when_retries_exhausted() -> anyhow::Result<()> {
+        // 脚本为空时 MockBackend 每次都返回错误
+        let backend = MockBackend::new();
+
+        let mut config = mock_config(10)?;
+        config.retry_policy.max_attempts = 2;
+
+        let agent = ReActAgent::with_backend(config, backend.clone());
+        let (results, status) = collect_results(agent.invoke("问题").await?).await;
+        assert_eq!(status, RunStatus::Error);
+
+        let error = results.last().unwrap().as_ref().unwrap_err();
+        assert!(error.to_string().contains("已重试 1 次"));
+        assert_eq!(backend.requests().len(), 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_react_agent_streams_content_deltas() -> anyhow::Result<()> {
+        let backend = MockBackend::new();
+        backend.push_chunks(response_into_chunks(
+            MockBackend::text_response("先搜索资料"),
+            Some(2),
+        ));
+        backend.push_chunks(response_into_chunks(
+            MockBackend::tool_calls_response([("call_1", "finish", r#"{"result":"done"}"#)]),
+            Some(4),
+        ));
+
+        let agent = ReActAgent::with_backend(mock_config(10)?, backend.clone());
+        let (events, status) = collect_events(agent, "问题").await?;
+        assert_eq!(status, RunStatus::Completed);
+
+        let deltas = events
+            .iter()
+            .filter_map(|event| match event {
+                AgentEvent::ContentDelta { step: 1, delta } => Some(delta.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(deltas, vec!["先搜", "索资", "料"]);
+
+        // 拼装后的完整回复放入短期记忆
+        let requests = backend.requests();
+        assert_eq!(requests[0].stream, Some(true));
+        let assistant: ChatCompletionRequestMessage =
+            ChatCompletionRequestAssistantMessageArgs::default()
+                .content("先搜索资料")
+                .build()?
+                .into();
+        assert_eq!(requests[1].messages[1], assistant);
+
+        // 分片到达的工具参数被完整拼装
+        assert_eq!(
+            events.last(),
+            Some(&AgentEvent::FinalAnswer {
+                answer: "done".to_string()
+            })
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_react_agent_without_stream() -> anyhow::Result<()> {
+        let backend = MockBackend::with_responses([
+            MockBackend::text_response("思考"),
+            MockBackend::tool_calls_response([("call_1", "finish", r#"{"result":"done"}"#)]),
+        ]);
+
+        let mut config = mock_config(10)?;
+        config.stream = false;
+
+        let agent = ReActAgent::with_backend(config, backend.clone());
+        let (events, status) = collect_events(agent, "问题").await?;
+        assert_eq!(status, RunStatus::Completed);
+
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, AgentEvent::ContentDelta { .. })));
+        assert!(events.contains(&responded(Some("思考"), vec![])));
+        assert_eq!(backend.requests()[0].stream, None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_react_agent_cancelled_before_start() -> anyhow::Result<()> {
+        let backend = MockBackend::with_responses([MockBackend::text_response("不应被请求")]);
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+
+        let agent = ReActAgent::with_backend(mock_config(10)?, backend.clone());
+        let (results, status) =
+            collect_results(agent.invoke_with_cancellation("问题", cancel).await?).await;
+        let events = results.into_iter().collect::<anyhow::Result<Vec<_>>>()?;
+
+        assert_eq!(status, RunStatus::Cancelled);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0], user("问题"));
+        match &events[1] {
+            AgentEvent::Cancelled { step, transcript } => {
+                assert_eq!(*step, 1);
+                // 只有系统消息
+                assert_eq!(transcript.len(), 1);
+            }
+            event => panic!("unexpected event: {:?}", event),
+        }
+        assert!(backend.requests().is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_react_agent_cancel_aborts_in_flight_wait() -> anyhow::Result<()> {
+        let backend = MockBackend::new();
+        backend.push_error(BackendError::Transient("overloaded".to_string()));
+        backend.push_response(MockBackend::text_response("不应被请求"));
+
+        // 重试等待时间足够长，只有取消才能让运行结束
+        let mut config = mock_config(10)?;
+        config.retry_policy = RetryPolicy {
+            initial_backoff: Duration::from_secs(3600),
+            jitter: false,
+            ..Default::default()
+        };
+
+        let cancel = CancellationToken::new();
+        let agent = ReActAgent::with_backend(config, backend.clone());
+        let mut stream = agent
+            .invoke_with_cancellation("问题", cancel.clone())
+            .await?;
+
+        let mut events = Vec::new();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while let Some(event) = stream.next().await {
+                let event = event?;
+                if let AgentEvent::Retrying { .. } = event {
+                    cancel.cancel();
+                }
+                events.push(event);
+            }
+            anyhow::Ok(())
+        })
+        .await??;
+
+        assert!(matches!(events[2], AgentEvent::Retrying { attempt: 1, .. }));
+        assert!(matches!(events[3], AgentEvent::Cancelled { step: 1, .. }));
+        assert_eq!(
+            events.last(),
+            Some(&AgentEvent::RunFinished {
+                status: RunStatus::Cancelled
+            })
+        );
+        assert_eq!(events.len(), 5);
+        assert_eq!(backend.requests().len(), 1);
+        assert_eq!(backend.remaining(), 1);
+
+        Ok(())
+    }
+
+    fn checkpoint_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("my-agent-{}-{}.json", name, std::process::id()))
+    }
+
+    #[tokio::test]
+    async fn test_react_agent_resumes_from_checkpoint() -> anyhow::Result<()> {
+        let path = checkpoint_path("resume");
+
+        // 第二轮请求大模型时出错，模拟进程在运行中途退出
+        let backend = MockBackend::with_responses([MockBackend::tool_calls_response([(
+            "call_1",
+            "unknown_tool",
+            "{}",
+        )])]);
+        backend.push_error(BackendError::Fatal("crashed".to_string()));
+
+        let mut config = mock_config(10)?;
+        config.checkpoint_path = Some(path.clone());
+
+        let agent = ReActAgent::with_backend(config.clone(), backend);
+        let (results, status) = collect_results(agent.invoke("问题").await?).await;
+        assert_eq!(status, RunStatus::Error);
+        assert!(results.last().unwrap().is_err());
+
+        let checkpoint = Checkpoint::load(&path)?;
+        assert_eq!(checkpoint.step(), 2);
+        assert_eq!(checkpoint.question(), "问题");
+        assert_eq!(checkpoint.tool_failures, 1);
+        assert_eq!(checkpoint.messages.len(), 3);
+        assert!(checkpoint.pending_tool_calls.is_empty());
+
+        let backend = MockBackend::with_responses([MockBackend::tool_calls_response([(
+            "call_2",
+            "finish",
+            r#"{"result":"done"}"#,
+        )])]);
+
+        let agent = ReActAgent::with_backend(config, backend.clone());
+        let (results, status) = collect_results(agent.resume(checkpoint.clone()).await?).await;
+        let events = results.into_iter().collect::<anyhow::Result<Vec<_>>>()?;
+        assert_eq!(status, RunStatus::Completed);
+
+        assert_eq!(events[0], AgentEvent::Resumed { step: 2 });
+        assert_eq!(events[1], step(2, 10));
+        assert_eq!(
+            events.last(),
+            Some(&AgentEvent::FinalAnswer {
+                answer: "done".to_string()
+            })
+        );
+
+        // 恢复后的请求带有检查点中的全部消息
+        assert_eq!(backend.requests()[0].messages, checkpoint.messages);
+
+        // 运行完成后删除检查点
+        assert!(!path.exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_react_agent_resume_runs_pending_tool_calls() -> anyhow::Result<()> {
+        let path = checkpoint_path("pending");
+
+        // 保存检查点后、执行工具前退出
+        let backend = MockBackend::with_responses([MockBackend::tool_calls_response([(
+            "call_1",
+            "finish",
+            r#"{"result":"done"}"#,
+        )])]);
+
+        let mut config = mock_config(10)?;
+        config.checkpoint_path = Some(path.clone());
+
+        let agent = ReActAgent::with_backend(config.clone(), backend);
+        let mut stream = agent.invoke("问题").await?;
+        while let Some(event) = stream.next().await {
+            if let AgentEvent::ToolCallStarted { .. } = event? {
+                break;
+            }
+        }
+        drop(stream);
+
+        let checkpoint = Checkpoint::load(&path)?;
+        assert_eq!(checkpoint.step(), 1);
+        assert_eq!(checkpoint.pending_tool_calls.len(), 1);
+
+        let backend = MockBackend::new();
+        let agent = ReActAgent::with_backend(config, backend.clone());
+        let (results, status) = collect_results(agent.resume(checkpoint).await?).await;
+        let events = results.into_iter().collect::<anyhow::Result<Vec<_>>>()?;
+        assert_eq!(status, RunStatus::Completed);
+
+        // 直接执行未完成的工具调用，不再请求大模型
+        assert!(backend.requests().is_empty());
+        assert_eq!(
+            events.last(),
+            Some(&AgentEvent::FinalAnswer {
+                answer: "done".to_string()
+            })
+        );
+        assert!(!path.exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_react_agent_wraps_up_when_budget_exhausted() -> anyhow::Result<()> {
+        let mut response = MockBackend::tool_calls_response([("call_1", "unknown_tool", "{}")]);
+        response.usage = Some(CompletionUsage {
+            prompt_tokens: 1_000,
+            completion_tokens: 100,
+            total_tokens: 1_100,
+        });
+
+        let backend = MockBackend::with_responses([
+            response,
+            MockBackend::tool_calls_response([("call_2", "finish", r#"{"result":"summary"}"#)]),
+            MockBackend::text_response("不应被请求"),
+        ]);
+
+        let mut config = mock_config(10)?;
+        config.budget = Budget {
+            max_cost: Some(0.015),
+            ..Default::default()
+        };
+        config
+            .prices
+            .insert("mock-model".to_string(), ModelPrice::new(10.0, 20.0));
+
+        let agent = ReActAgent::with_backend(config, backend.clone());
+        let (events, status) = collect_events(agent, "问题").await?;
+        assert_eq!(status, RunStatus::Budget);
+
+        // 第一轮费用 0.012，预计下一轮会超出 0.015 的预算
+        let (reason, usage) = events
+            .iter()
+            .find_map(|event| match event {
+                AgentEvent::BudgetExceeded { reason, usage } => Some((reason.clone(), *usage)),
+                _ => None,
+            })
+            .unwrap();
+        assert!(reason.contains("费用预算"));
+        assert_eq!(usage.prompt_tokens, 1_000);
+        assert_eq!(usage.completion_tokens, 100);
+        assert!((usage.cost - 0.012).abs() < 1e-9);
+
+        assert_eq!(
+            events.last(),
+            Some(&AgentEvent::FinalAnswer {
+                answer: "summary".to_string()
+            })
+        );
+        assert!(!events.contains(&step(2, 10)));
+
+        // 收尾请求的最后一条消息要求大模型立即给出答案
+        let requests = backend.requests();
+        assert_eq!(requests.len(), 2);
+        match requests[1].messages.last() {
+            Some(ChatCompletionRequestMessage::User(message)) => match &message.content {
+                ChatCompletionRequestUserMessageContent::Text(text) => {
+                    assert!(text.starts_with(&reason));
+                    assert!(text.contains("finish"));
+                }
+                content => panic!("unexpected content: {:?}", content),
+            },
+            message => panic!("expected user message, got {:?}", message),
+        }
+
+        Ok(())
+    }
+
+    // 每条消息算一个 token，方便控制何时超出上下文上限
+    struct MessageTokenizer;
+
+    impl Tokenizer for MessageTokenizer {
+        fn count(&self, _text: &str) -> usize {
+            1
+        }
+    }
+
+    #[tokio::test]
+    async fn test_react_agent_summarizes_evicted_history() -> anyhow::Result<()> {
+        let backend = MockBackend::with_responses([
+            MockBackend::text_response("第一步"),
+            MockBackend::text_response("第二步"),
+            MockBackend::text_response("已写入 report.md，还需补充结论"),
+            MockBackend::tool_calls_response([("call_1", "finish", r#"{"result":"done"}"#)]),
+        ]);
+
+        let mut config = mock_config(10)?;
+        config.context_window = Some(2);
+        config.memory_strategy = MemoryStrategy::Summarize;
+
+        let agent =
+            ReActAgent::with_backend(config, backend.clone()).with_tokenizer(MessageTokenizer);
+        let (events, status) = collect_events(agent, "问题").await?;
+        assert_eq!(status, RunStatus::Completed);
+
+        // 第三轮请求前超出上限，最早的一条消息被总结
+        assert!(events.contains(&AgentEvent::MemoryCompacted {
+            evicted: 1,
+            summary: Some("已写入 report.md，还需补充结论".to_string()),
+        }));
+
+        let requests = backend.requests();
+        assert_eq!(requests.len(), 4);
+
+        // 总结请求不带工具，包含被移除的消息
+        let summarize = &requests[2];
+        assert!(summarize.tools.is_none());
+        match &summarize.messages[..] {
+            [ChatCompletionRequestMessage::User(message)] => match &message.content {
+                ChatCompletionRequestUserMessageContent::Text(text) => {
+                    assert!(text.contains("[助手] 第一步"));
+                    assert!(!text.contains("第二步"));
+                }
+                content => panic!("unexpected content: {:?}", content),
+            },
+            messages => panic!("unexpected messages: {:?}", messages),
+        }
+
+        // 总结替代被移除的消息，位于历史消息的最前面
+        let messages = &requests[3].messages;
+        assert_eq!(messages.len(), 3);
+        match &messages[1] {
+            ChatCompletionRequestMessage::User(message) => match &message.content {
+                ChatCompletionRequestUserMessageContent::Text(text) => {
+                    assert!(text.contains("已写入 report.md，还需补充结论"));
+                }
+                content => panic!("unexpected content: {:?}", content),
+            },
+            message => panic!("expected user message, got {:?}", message),
+        }
+        let assistant: ChatCompletionRequestMessage =
+            ChatCompletionRequestAssistantMessageArgs::default()
+                .content("第二步")
+                .build()?
+                .into();
+        assert_eq!(messages[2], assistant);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_react_agent_recalls_and_saves_long_memory() -> anyhow::Result<()> {
+        let path = std::env::temp_dir().join(format!(
+            "my-agent-react-long-memory-{}.json",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+
+        let long_memory = Arc::new(LongMemory::open(
+            &path,
+            Arc::new(crate::memory::HashEmbedder::default()),
+        )?);
+        long_memory
+            .add(MemoryKind::Note, "周杰伦出生于 1979 年")
+            .await?;
+
+        let backend = MockBackend::with_responses([
+            MockBackend::tool_calls_response([(
+                "call_1",
+                "memory_save",
+                r#"{"content":"周杰伦今年 45 岁"}"#,
+            )]),
+            MockBackend::tool_calls_response([("call_2", "finish", r#"{"result":"45 岁"}"#)]),
+        ]);
+
+        // 先设置工具集合再设置长期记忆，记忆工具同样使用该长期记忆
+        let agent = ReActAgent::with_backend(mock_config(10)?, backend.clone())
+            .with_tools(ToolRegistry::builtin())
+            .with_long_memory(long_memory.clone());
+        let (_, status) = collect_events(agent, "周杰伦今年多大").await?;
+        assert_eq!(status, RunStatus::Completed);
+
+        // 相关的记忆写入系统消息
+        match &backend.requests()[0].messages[0] {
+            ChatCompletionRequestMessage::System(message) => {
+                assert!(message.content.contains("- 周杰伦出生于 1979 年"));
+            }
+            message => panic!("expected system message, got {:?}", message),
+        }
+
+        // 完成的任务写入长期记忆并保存到文件
+        let reopened = LongMemory::open(&path, Arc::new(crate::memory::HashEmbedder::default()))?;
+        std::fs::remove_file(&path)?;
+
+        // memory_save 工具保存到同一个长期记忆
+        let entries = reopened.entries();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[1].kind, MemoryKind::Note);
+        assert_eq!(entries[1].content, "周杰伦今年 45 岁");
+        assert_eq!(entries[2].kind, MemoryKind::TaskOutcome);
+        assert_eq!(entries[2].content, "问题: 周杰伦今年多大\n答案: 45 岁");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_react_agent_records_run_history() -> anyhow::Result<()> {
+        let backend = MockBackend::with_responses([
+            MockBackend::tool_calls_response([("call_1", "unknown", "{}")]),
+            MockBackend::tool_calls_response([("call_2", "finish", r#"{"result":"42"}"#)]),
+        ]);
+
+        let history = Arc::new(RunHistory::open_in_memory()?);
+        let agent = ReActAgent::with_backend(mock_config(10)?, backend.clone())
+            .with_history(history.clone());
+        let (_, status) = collect_events(agent, "问题").await?;
+        assert_eq!(status, RunStatus::Completed);
+
+        let runs = history.list(10)?;
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].question, "问题");
+        assert_eq!(runs[0].status, RunStatus::Completed);
+
+        let record = history.fetch(runs[0].id)?.unwrap();
+        assert_eq!(record.answer.as_deref(), Some("42"));
+        assert_eq!(record.error, None);
+        assert_eq!(record.config.model, "mock-model");
+        assert!(record.finished_at >= record.started_at);
+
+        // 系统消息、两轮工具调用和结果
+        assert_eq!(record.messages.len(), 5);
+        assert_eq!(record.messages[..3], backend.requests()[1].messages);
+
+        assert_eq!(
+            record
+                .tool_calls
+                .iter()
+                .map(|call| (
+                    call.name.as_str(),
+                    call.result.as_deref(),
+                    call.error.is_some()
+                ))
+                .collect::<Vec<_>>(),
+            vec![("unknown", None, true), ("finish", Some("42"), false)]
+        );
+        assert!(record
+            .tool_calls
+            .iter()
+            .all(|call| call.finished_at.is_some()));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_react_agent_records_full_transcript_and_usage() -> anyhow::Result<()> {
+        let usage = Some(CompletionUsage {
+            prompt_tokens: 10,
+            completion_tokens: 1,
+            total_tokens: 11,
+        });
+        let mut first = MockBackend::text_response("第一步");
+        first.usage = usage.clone();
+        let mut wrap_up =
+            MockBackend::tool_calls_response([("call_1", "finish", r#"{"result":"done"}"#)]);
+        wrap_up.usage = usage;
+
+        let backend = MockBackend::with_responses([first, wrap_up]);
+
+        // 短期记忆只保留最近的对话，达到最大调用轮数后收尾
+        let mut config = mock_config(1)?;
+        config.context_window = Some(2);
+
+        let history = Arc::new(RunHistory::open_in_memory()?);
+        let agent = ReActAgent::with_backend(config, backend.clone())
+            .with_tokenizer(MessageTokenizer)
+            .with_history(history.clone());
+        let (_, status) = collect_events(agent, "问题").await?;
+        assert_eq!(status, RunStatus::StepLimit);
+
+        let record = history.fetch(history.list(1)?[0].id)?.unwrap();
+
+        // 运行记录包含已被移出短期记忆的消息
+        let requests = backend.requests();
+        assert!(requests[1].messages.len() < record.messages.len());
+        assert!(matches!(
+            record.messages[0],
+            ChatCompletionRequestMessage::System(_)
+        ));
+        assert!(serde_json::to_string(&record.messages)?.contains("第一步"));
+
+        // 收尾请求的用量同样计入
+        assert_eq!(record.usage.prompt_tokens, 20);
+        assert_eq!(record.usage.completion_tokens, 2);
+
+        // 多轮对话中每轮的运行记录只包含本轮的消息
+        let backend = MockBackend::with_responses([
+            MockBackend::tool_calls_response([("call_1", "finish", r#"{"result":"第一轮"}"#)]),
+            MockBackend::tool_calls_response([("call_2", "finish", r#"{"result":"第二轮"}"#)]),
+        ]);
+        let history = Arc::new(RunHistory::open_in_memory()?);
+        let session = ReActAgent::with_backend(mock_config(10)?, backend.clone())
+            .with_history(history.clone())
+            .session();
+        for question in ["问题", "追问"] {
+            let (_, status) = collect_results(session.send(question).await?).await;
+            assert_eq!(status, RunStatus::Completed);
+        }
+
+        let runs = history.list(10)?;
+        let record = history.fetch(runs[0].id)?.unwrap();
+        assert_eq!(record.question, "追问");
+        // 系统消息、追问、工具调用和结果
+        assert_eq!(record.messages.len(), 4);
+        assert!(!serde_json::to_string(&record.messages)?.contains("第一轮"));
+
+        Ok(())
+    }
+
+    struct Weather;
+
+    impl crate::tools::Tool for Weather {
+        fn name(&self) -> &str {
+            "weather"
+        }
+
+        fn description(&self) -> &str {
+            "查询城市的天气"
+        }
+
+        fn parameters(&self) -> serde_json::Value {
+            serde_json::json!({
+                "type": "object",
+                "properties": { "city": { "type": "string" } },
+                "required": ["city"],
+            })
+        }
+
+        fn execute(
+            &self,
+            arguments: serde_json::Value,
+        ) -> futures::future::BoxFuture<'_, anyhow::Result<String>> {
+            Box::pin(async move { Ok(format!("{} 晴", arguments["city"].as_str().unwrap_or("?"))) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_react_agent_uses_custom_tools() -> anyhow::Result<()> {
+        let backend = MockBackend::with_responses([
+            MockBackend::tool_calls_response([
+                ("call_1", "weather", r#"{"city":"北京"}"#),
+                ("call_2", "search", r#"{"query":"北京天气"}"#),
+            ]),
+            MockBackend::tool_calls_response([("call_3", "finish", r#"{"result":"北京 晴"}"#)]),
+        ]);
+
+        let tools = ToolRegistry::builtin().without("search").with(Weather);
+        let agent = ReActAgent::with_backend(mock_config(10)?, backend.clone()).with_tools(tools);
+        let (events, status) = collect_events(agent, "北京天气怎么样").await?;
+        assert_eq!(status, RunStatus::Completed);
+
+        assert!(events.contains(&AgentEvent::ToolCallFinished {
+            id: "call_1".to_string(),
+            name: "weather".to_string(),
+            result: "北京 晴".to_string(),
+            sources: Vec::new(),
+        }));
+        // 被移除的内置工具不可调用
+        assert!(events.contains(&AgentEvent::ToolFailed {
+            id: "call_2".to_string(),
+            name: "search".to_string(),
+            kind: ToolErrorKind::UnknownTool,
+            error: "Unknown tool: search".to_string(),
+        }));
+
+        // 请求中只包含注册的工具
+        let names = backend.requests()[0]
+            .tools
+            .iter()
+            .flatten()
+            .map(|tool| tool.function.name.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                "file_write",
+                "memory_save",
+                "memory_search",
+                "weather",
+                "finish"
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_react_agent_prompted_tool_mode() -> anyhow::Result<()> {
+        let backend = MockBackend::with_responses([
+            MockBackend::text_response("我需要先查一下天气"),
+            // 修复请求仍然没有返回 JSON
+            MockBackend::text_response("无法修复"),
+            MockBackend::text_response(
+                "```json\n{'thoughts': {'speak': '查询天气'}, 'command': {'name': 'weather', 'args': {'city': '北京',}}}\n```",
+            ),
+            MockBackend::text_response(r#"{"command": {"name": "finish", "args": {"result": "晴"}}}"#),
+        ]);
+        let mut config = mock_config(5)?;
+        config.tool_mode = ToolMode::Prompted;
+        let agent = ReActAgent::with_backend(config, backend.clone());
+
+        let (events, status) = collect_events(agent, "北京天气").await?;
+        assert_eq!(status, RunStatus::Completed);
+
+        let text = |content: &str| -> anyhow::Result<ChatCompletionRequestMessage> {
+            Ok(ChatCompletionRequestUserMessageArgs::default()
+                .content(content)
+                .build()?
+                .into())
+        };
+
+        assert!(events.contains(&AgentEvent::InvalidResponse {
+            step: 1,
+            error: "expected value at line 1 column 1".to_string(),
+        }));
+        assert!(events.contains(&AgentEvent::ToolCallStarted {
+            id: "command_2".to_string(),
+            name: "weather".to_string(),
+            arguments: r#"{"city":"北京"}"#.to_string(),
+        }));
+        assert!(events.contains(&AgentEvent::ToolFailed {
+            id: "command_2".to_string(),
+            name: "weather".to_string(),
+            kind: ToolErrorKind::UnknownTool,
+            error: "Unknown tool: weather".to_string(),
+        }));
+        assert_eq!(
+            events.last(),
+            Some(&AgentEvent::FinalAnswer {
+                answer: "晴".to_string()
+            })
+        );
+
+        let requests = backend.requests();
+        assert_eq!(requests.len(), 4);
+        // 工具写入系统消息，请求中不包含工具定义
+        assert!(requests.iter().all(|request| request.tools.is_none()));
+        match &requests[0].messages[0] {
+            ChatCompletionRequestMessage::System(message) => {
+                assert!(message.content.contains("5. finish: 完成用户的任务目标"));
+            }
+            message => panic!("expected system message, got {:?}", message),
+        }
+        assert!(format!("{:?}", requests[1].messages).contains("修复下面的数据内容为JSON格式"));
+
+        // 回复作为助手消息，格式错误和命令结果作为用户消息
+        let messages = &requests[3].messages;
+        assert_eq!(messages.len(), 5);
+        let assistant: ChatCompletionRequestMessage =
+            ChatCompletionRequestAssistantMessageArgs::default()
+                .content("我需要先查一下天气")
+                .build()?
+                .into();
+        assert_eq!(messages[1], assistant);
+        assert_eq!(
+            messages[2],
+            text("你的回复无法解析为命令: expected value at line 1 column 1\n\n请严格按照要求的 JSON 格式重新回复，并且只包含一个命令。\n")?
+        );
+        assert_eq!(
+            messages[4],
+            text(&format!(
+                "命令 weather 的执行结果:\n\n{}\n",
+                ToolError::unknown_tool("weather").to_content()
+            ))?
+        );
+
+        Ok(())
+    }
 }
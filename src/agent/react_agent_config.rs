@@ -1,10 +1,16 @@
-use super::Language;
+use super::{Budget, Language, ModelPrice, ToolMode};
+use crate::llm::RetryPolicy;
+use crate::memory::MemoryStrategy;
 use derive_builder::Builder;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, path::PathBuf, time::Duration};
 use url::Url;
 
-#[derive(Builder, Debug, Clone, PartialEq)]
+#[derive(Builder, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[builder(try_setter, setter(into, prefix = "set"))]
 pub struct ReActAgentConfig {
+    // 不写入检查点，恢复运行时使用当前 Agent 的密钥
+    #[serde(skip)]
     pub(crate) api_key: String,
     pub(crate) base_url: Url,
     pub(crate) model: String,
@@ -16,12 +22,56 @@ pub struct ReActAgentConfig {
     pub(crate) max_steps: usize,
     #[builder(default = "0.3")]
     pub(crate) temperature: f32,
+    // 是否使用流式接口，开启后模型输出会逐字通过事件流返回
+    #[builder(default = "true")]
+    pub(crate) stream: bool,
+    // 请求大模型失败时的重试策略，重试不消耗调用轮数
+    #[builder(default)]
+    pub(crate) retry_policy: RetryPolicy,
+    // 单次运行允许的工具调用失败次数，超过后停止运行
+    #[builder(default = "5")]
+    pub(crate) max_tool_failures: usize,
+    // 同一轮中最多同时执行的工具调用数量
+    #[builder(default = "4")]
+    pub(crate) max_concurrent_tools: usize,
+    // 工具执行的默认超时时间，None 表示不限制
+    #[builder(default = "Some(Duration::from_secs(120))")]
+    pub(crate) tool_timeout: Option<Duration>,
+    // 按工具名称单独设置的超时时间，优先于 tool_timeout
+    #[builder(default)]
+    pub(crate) tool_timeouts: HashMap<String, Duration>,
+    // 单次运行的 token 和费用预算，即将超出时要求大模型立即给出最终答案
+    #[builder(default)]
+    pub(crate) budget: Budget,
+    // 各模型的价格表，用于估算费用
+    #[builder(default)]
+    pub(crate) prices: HashMap<String, ModelPrice>,
+    // 短期记忆的 token 上限，默认不限制，应小于模型的上下文长度
+    #[builder(default)]
+    pub(crate) context_window: Option<usize>,
+    // 超出上下文上限时的处理方式，默认丢弃最早的对话
+    #[builder(default)]
+    pub(crate) memory_strategy: MemoryStrategy,
+    // 向大模型提供工具的方式，不支持工具调用的模型使用 ToolMode::Prompted
+    #[builder(default)]
+    pub(crate) tool_mode: ToolMode,
+    // 每轮开始时从长期记忆中检索的条数
+    #[builder(default = "3")]
+    pub(crate) long_memory_top_k: usize,
+    // 检查点文件路径，设置后每轮结束时保存运行状态，可通过 ReActAgent::resume 恢复
+    #[builder(default)]
+    pub(crate) checkpoint_path: Option<PathBuf>,
 }
 
 impl ReActAgentConfig {
     pub fn builder() -> ReActAgentConfigBuilder {
         ReActAgentConfigBuilder::default()
     }
+
+    /// 指定工具的超时时间
+    pub(crate) fn tool_timeout_for(&self, name: &str) -> Option<Duration> {
+        self.tool_timeouts.get(name).copied().or(self.tool_timeout)
+    }
 }
 
 #[cfg(test)]
@@ -43,6 +93,48 @@ mod tests {
         assert_eq!(config.language, Language::Chinese);
         assert_eq!(config.language.to_string(), "chinese");
         assert_eq!(config.max_steps, 10);
+        assert!(config.stream);
+        assert_eq!(config.retry_policy, RetryPolicy::default());
+        assert_eq!(config.max_tool_failures, 5);
+        assert_eq!(config.max_concurrent_tools, 4);
+        assert_eq!(config.tool_timeout, Some(Duration::from_secs(120)));
+        assert_eq!(config.checkpoint_path, None);
+        assert_eq!(config.budget, Budget::default());
+        assert_eq!(config.context_window, None);
+        assert_eq!(config.memory_strategy, MemoryStrategy::Truncate);
+        assert_eq!(config.long_memory_top_k, 3);
+        assert_eq!(config.tool_mode, ToolMode::Native);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tool_timeout_for() -> anyhow::Result<()> {
+        let config = ReActAgentConfig::builder()
+            .set_api_key("my_api_key")
+            .set_model("moonshot-v1-8k")
+            .try_set_base_url("http://localhost")?
+            .set_tool_timeout(Duration::from_secs(10))
+            .set_tool_timeouts([("search".to_string(), Duration::from_secs(30))])
+            .build()?;
+
+        assert_eq!(
+            config.tool_timeout_for("search"),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            config.tool_timeout_for("file_write"),
+            Some(Duration::from_secs(10))
+        );
+
+        let config = ReActAgentConfig::builder()
+            .set_api_key("my_api_key")
+            .set_model("moonshot-v1-8k")
+            .try_set_base_url("http://localhost")?
+            .set_tool_timeout(None)
+            .build()?;
+
+        assert_eq!(config.tool_timeout_for("search"), None);
 
         Ok(())
     }
@@ -1,13 +1,17 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+/// 提示词模式下大模型回复的格式，见 response_format.prompt
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Response {
+    // 较小的模型常常省略思考过程，缺少时使用空内容
+    #[serde(default)]
     pub thoughts: Thoughts,
     pub command: Command,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Thoughts {
     pub text: String,
     pub reasoning: String,
@@ -19,6 +23,7 @@ pub struct Thoughts {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Command {
     pub name: String,
+    #[serde(default)]
     pub args: Value,
 }
 
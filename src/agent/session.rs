@@ -0,0 +1,382 @@
+use super::{react_agent::RunState, AgentEventStream, CancellationToken, ReActAgent};
+use crate::{
+    llm::{ChatBackend, OpenAIBackend},
+    memory::ShortMemory,
+    planning::Planning,
+};
+use anyhow::Result;
+use async_openai::types::ChatCompletionRequestMessage;
+use std::sync::Arc;
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+/// 多轮对话，短期记忆在各轮之间保留，用户可以在上一轮的基础上继续提问
+///
+/// 每轮对话运行期间持有会话状态，同一会话的多轮对话依次执行
+pub struct Session<B = OpenAIBackend> {
+    agent: ReActAgent<B>,
+    state: Arc<Mutex<SessionState>>,
+}
+
+struct SessionState {
+    short_memory: ShortMemory,
+    // 已提出的问题，按提问顺序排列
+    questions: Vec<String>,
+}
+
+impl<B> Session<B>
+where
+    B: ChatBackend + 'static,
+{
+    pub fn new(agent: ReActAgent<B>) -> Self {
+        let state = SessionState {
+            short_memory: agent.short_memory(),
+            questions: Vec::new(),
+        };
+
+        Self {
+            agent,
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    /// 发送一条消息，开始新一轮对话
+    pub async fn send(&self, message: &str) -> Result<AgentEventStream> {
+        self.send_with_cancellation(message, CancellationToken::new())
+            .await
+    }
+
+    /// 发送一条消息，cancel 被取消时中止本轮对话，已产生的消息保留在会话中
+    pub async fn send_with_cancellation(
+        &self,
+        message: &str,
+        cancel: CancellationToken,
+    ) -> Result<AgentEventStream> {
+        let language = self.agent.config.language.to_string();
+        let planning = Planning::try_new()?.with_tool_mode(self.agent.config.tool_mode);
+        let memories = self.agent.recall(message).await?;
+        let mut state = self.state.clone().lock_owned().await;
+
+        // 当前消息作为任务目标，之前的问题和相关的长期记忆作为背景写入系统消息
+        let system_message = planning.build_system_message(
+            message,
+            &state.questions,
+            &memories,
+            &self.agent.tools,
+            &language,
+        )?;
+        // 运行记录只包含本轮对话的消息
+        state.short_memory.take_transcript();
+        state.short_memory.append(system_message.into());
+
+        // 追问时将消息作为用户消息发送给大模型，标记新一轮对话的开始
+        if !state.questions.is_empty() {
+            let user_message = planning.build_user_message(message)?;
+            state.short_memory.append(user_message.into());
+        }
+
+        state.questions.push(message.to_string());
+
+        let short_memory = OwnedMutexGuard::map(state, |state| &mut state.short_memory);
+        self.agent
+            .run(planning, short_memory, message, RunState::new(), cancel)
+    }
+
+    /// 会话中已提出的问题
+    pub async fn questions(&self) -> Vec<String> {
+        self.state.lock().await.questions.clone()
+    }
+
+    /// 会话的完整对话记录，本轮对话运行期间会等待其结束
+    pub async fn transcript(&self) -> Vec<ChatCompletionRequestMessage> {
+        self.state.lock().await.short_memory.messages()
+    }
+
+    /// 清空对话记录，重新开始
+    pub async fn reset(&self) {
+        let mut state = self.state.lock().await;
+        state.short_memory = self.agent.short_memory();
+        state.questions.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agent::{AgentEvent, ReActAgentConfig, RunStatus},
+        llm::MockBackend,
+        tools::{ToolRegistry, TypedTool},
+    };
+    use async_openai::types::{
+        ChatCompletionRequestUserMessageContent, CreateChatCompletionRequest,
+    };
+    use futures::{future::BoxFuture, StreamExt};
+    use serde::Deserialize;
+    use std::time::Duration;
+
+    /// 一直执行到被取消的工具
+    #[derive(Deserialize, crate::tools::Tool)]
+    struct SlowArgs {}
+
+    struct Slow;
+
+    impl TypedTool for Slow {
+        type Args = SlowArgs;
+
+        fn call(&self, _args: SlowArgs) -> BoxFuture<'_, anyhow::Result<String>> {
+            Box::pin(async {
+                tokio::time::sleep(Duration::from_secs(3600)).await;
+                Ok(String::new())
+            })
+        }
+    }
+
+    fn mock_agent(backend: MockBackend) -> anyhow::Result<ReActAgent<MockBackend>> {
+        let config = ReActAgentConfig::builder()
+            .set_api_key("my_api_key")
+            .set_model("mock-model")
+            .try_set_base_url("http://localhost")?
+            .build()?;
+
+        Ok(ReActAgent::with_backend(config, backend))
+    }
+
+    async fn send(
+        session: &Session<MockBackend>,
+        message: &str,
+    ) -> anyhow::Result<Vec<AgentEvent>> {
+        let mut events = session
+            .send(message)
+            .await?
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        // 每轮对话都以结束事件结尾
+        assert_eq!(
+            events.pop(),
+            Some(AgentEvent::RunFinished {
+                status: RunStatus::Completed
+            })
+        );
+
+        Ok(events)
+    }
+
+    // 每个 tool_call 都要有对应的工具消息，否则兼容 OpenAI 的接口会拒绝请求
+    fn assert_tool_calls_answered(request: &CreateChatCompletionRequest) {
+        let mut pending = Vec::new();
+
+        for message in &request.messages {
+            match message {
+                ChatCompletionRequestMessage::Assistant(message) => {
+                    assert!(pending.is_empty(), "unanswered tool calls: {:?}", pending);
+                    pending = message
+                        .tool_calls
+                        .iter()
+                        .flatten()
+                        .map(|tool_call| tool_call.id.clone())
+                        .collect();
+                }
+                ChatCompletionRequestMessage::Tool(message) => {
+                    pending.retain(|id| *id != message.tool_call_id);
+                }
+                _ => {}
+            }
+        }
+
+        assert!(pending.is_empty(), "unanswered tool calls: {:?}", pending);
+    }
+
+    fn system_prompt(request: &CreateChatCompletionRequest) -> String {
+        match &request.messages[0] {
+            ChatCompletionRequestMessage::System(message) => message.content.clone(),
+            message => panic!("expected system message, got {:?}", message),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_session_keeps_memory_across_turns() -> anyhow::Result<()> {
+        let backend = MockBackend::with_responses([
+            MockBackend::tool_calls_response([("call_1", "finish", r#"{"result":"很长的答案"}"#)]),
+            MockBackend::tool_calls_response([("call_2", "finish", r#"{"result":"短答案"}"#)]),
+        ]);
+
+        let session = mock_agent(backend.clone())?.session();
+
+        let events = send(&session, "写一段介绍").await?;
+        assert_eq!(
+            events.last(),
+            Some(&AgentEvent::FinalAnswer {
+                answer: "很长的答案".to_string()
+            })
+        );
+
+        let events = send(&session, "再短一些").await?;
+        assert_eq!(
+            events.first(),
+            Some(&AgentEvent::UserMessage {
+                content: "再短一些".to_string()
+            })
+        );
+        assert_eq!(
+            events.last(),
+            Some(&AgentEvent::FinalAnswer {
+                answer: "短答案".to_string()
+            })
+        );
+
+        let requests = backend.requests();
+        assert_eq!(requests.len(), 2);
+
+        // 第一轮只有系统消息，问题作为任务目标
+        assert_eq!(requests[0].messages.len(), 1);
+        assert!(system_prompt(&requests[0]).contains("任务目标: 写一段介绍"));
+
+        // 第二轮保留第一轮的工具调用和结果，并追加追问
+        let system = system_prompt(&requests[1]);
+        assert!(system.contains("任务目标: 再短一些"));
+        assert!(system.contains("- 写一段介绍"));
+        assert_eq!(requests[1].messages.len(), 4);
+        assert!(matches!(
+            requests[1].messages[1],
+            ChatCompletionRequestMessage::Assistant(_)
+        ));
+        assert!(matches!(
+            requests[1].messages[2],
+            ChatCompletionRequestMessage::Tool(_)
+        ));
+        match &requests[1].messages[3] {
+            ChatCompletionRequestMessage::User(message) => assert_eq!(
+                message.content,
+                ChatCompletionRequestUserMessageContent::Text("再短一些".to_string())
+            ),
+            message => panic!("expected user message, got {:?}", message),
+        }
+
+        assert_eq!(session.questions().await, vec!["写一段介绍", "再短一些"]);
+        assert_eq!(session.transcript().await.len(), 6);
+
+        session.reset().await;
+        assert!(session.questions().await.is_empty());
+        assert!(session.transcript().await.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_session_drops_tool_calls_after_finish() -> anyhow::Result<()> {
+        let backend = MockBackend::with_responses([
+            MockBackend::tool_calls_response([
+                ("call_1", "finish", r#"{"result":"答案"}"#),
+                ("call_2", "search", r#"{"query":"Rust"}"#),
+            ]),
+            MockBackend::tool_calls_response([("call_3", "finish", r#"{"result":"追问的答案"}"#)]),
+        ]);
+
+        let session = mock_agent(backend.clone())?.session();
+
+        let events = send(&session, "写一段介绍").await?;
+        assert!(!events.iter().any(
+            |event| matches!(event, AgentEvent::ToolCallStarted { name, .. } if name == "search")
+        ));
+
+        send(&session, "再短一些").await?;
+
+        // 第二轮请求中第一轮的助手消息只包含已执行的结束工具调用
+        let requests = backend.requests();
+        assert_tool_calls_answered(&requests[1]);
+        match &requests[1].messages[1] {
+            ChatCompletionRequestMessage::Assistant(message) => {
+                assert_eq!(message.tool_calls.as_ref().map(Vec::len), Some(1))
+            }
+            message => panic!("expected assistant message, got {:?}", message),
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_session_continues_after_cancelled_tool_call() -> anyhow::Result<()> {
+        let backend = MockBackend::with_responses([
+            MockBackend::tool_calls_response([("call_1", "slow", "{}")]),
+            MockBackend::tool_calls_response([("call_2", "finish", r#"{"result":"答案"}"#)]),
+        ]);
+
+        let agent = mock_agent(backend.clone())?.with_tools(ToolRegistry::new().with(Slow));
+        let session = agent.session();
+
+        // 工具开始执行后取消本轮对话
+        let cancel = CancellationToken::new();
+        let mut stream = session
+            .send_with_cancellation("写一段介绍", cancel.clone())
+            .await?;
+        let mut status = None;
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while let Some(event) = stream.next().await {
+                match event? {
+                    AgentEvent::ToolCallStarted { .. } => cancel.cancel(),
+                    AgentEvent::RunFinished { status: finished } => status = Some(finished),
+                    _ => {}
+                }
+            }
+            anyhow::Ok(())
+        })
+        .await??;
+        drop(stream);
+        assert_eq!(status, Some(RunStatus::Cancelled));
+
+        send(&session, "继续").await?;
+
+        // 被取消的调用有对应的工具消息，第二轮请求仍然合法
+        let requests = backend.requests();
+        assert_tool_calls_answered(&requests[1]);
+        match &requests[1].messages[2] {
+            ChatCompletionRequestMessage::Tool(message) => {
+                assert_eq!(message.tool_call_id, "call_1");
+                assert!(message.content.contains("cancelled"));
+            }
+            message => panic!("expected tool message, got {:?}", message),
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_session_keeps_wrap_up_answer() -> anyhow::Result<()> {
+        let backend = MockBackend::with_responses([
+            MockBackend::text_response("思考"),
+            // 收尾时大模型直接回复答案，没有调用结束工具
+            MockBackend::text_response("收尾的答案"),
+            MockBackend::tool_calls_response([("call_1", "finish", r#"{"result":"追问的答案"}"#)]),
+        ]);
+
+        let mut agent = mock_agent(backend.clone())?;
+        agent.config.max_steps = 1;
+        let session = agent.session();
+
+        let events = session
+            .send("写一段介绍")
+            .await?
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        assert!(events.contains(&AgentEvent::FinalAnswer {
+            answer: "收尾的答案".to_string()
+        }));
+
+        send(&session, "再短一些").await?;
+
+        // 下一轮请求包含上一轮收尾时给出的答案
+        let requests = backend.requests();
+        assert!(requests[2].messages.iter().any(|message| matches!(
+            message,
+            ChatCompletionRequestMessage::Assistant(message)
+                if message.content.as_deref() == Some("收尾的答案")
+        )));
+
+        Ok(())
+    }
+}
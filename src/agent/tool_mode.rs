@@ -0,0 +1,39 @@
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+/// 向大模型提供工具的方式
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolMode {
+    /// 使用接口原生的工具调用（function calling）
+    #[default]
+    Native,
+    /// 在系统提示词中描述工具，大模型按 Response 的 JSON 格式回复命令，
+    /// 适用于不支持工具调用的本地模型
+    Prompted,
+}
+
+impl FromStr for ToolMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "native" => Ok(ToolMode::Native),
+            "prompted" => Ok(ToolMode::Prompted),
+            _ => Err(anyhow!("Invalid tool mode")),
+        }
+    }
+}
+
+impl Display for ToolMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ToolMode::Native => write!(f, "native"),
+            ToolMode::Prompted => write!(f, "prompted"),
+        }
+    }
+}
@@ -0,0 +1,263 @@
+use crate::utils::fnv1a;
+use anyhow::{anyhow, Result};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    fmt::{self, Display},
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Mutex,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CassetteMode {
+    /// 真实请求外部服务，并把请求和响应写入磁带
+    Record,
+    /// 不访问网络，按请求哈希从磁带中取出响应
+    Replay,
+}
+
+impl FromStr for CassetteMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "record" => Ok(CassetteMode::Record),
+            "replay" => Ok(CassetteMode::Replay),
+            _ => Err(anyhow!("Invalid cassette mode")),
+        }
+    }
+}
+
+impl Display for CassetteMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CassetteMode::Record => write!(f, "record"),
+            CassetteMode::Replay => write!(f, "replay"),
+        }
+    }
+}
+
+/// 一次完整的外部交互：请求类型（chat / tavily）、请求哈希、请求与响应内容
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    pub kind: String,
+    pub hash: String,
+    pub request: Value,
+    pub response: Value,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CassetteFile {
+    interactions: Vec<Interaction>,
+}
+
+#[derive(Debug, Default)]
+struct CassetteState {
+    interactions: Vec<Interaction>,
+    // 回放时记录哪些交互已经被使用过，相同请求按录制顺序依次回放
+    used: Vec<bool>,
+}
+
+/// 大模型和 Tavily 请求的录制/回放磁带，以 JSON 文件保存
+///
+/// 录制模式下每记录一次交互就写回文件，即使进程中途崩溃也能保留已发生的请求；
+/// 回放模式下完全不访问网络，可以用来复现问题或构建回归测试
+#[derive(Debug)]
+pub struct Cassette {
+    path: PathBuf,
+    mode: CassetteMode,
+    state: Mutex<CassetteState>,
+}
+
+impl Cassette {
+    /// 以录制模式打开磁带，已有的文件内容会被覆盖
+    pub fn record(path: impl AsRef<Path>) -> Result<Self> {
+        let cassette = Self {
+            path: path.as_ref().to_path_buf(),
+            mode: CassetteMode::Record,
+            state: Mutex::new(CassetteState::default()),
+        };
+        cassette.save()?;
+
+        Ok(cassette)
+    }
+
+    /// 以回放模式打开已录制的磁带
+    pub fn replay(path: impl AsRef<Path>) -> Result<Self> {
+        let content = fs::read_to_string(path.as_ref())?;
+        let file: CassetteFile = serde_json::from_str(&content)?;
+        let used = vec![false; file.interactions.len()];
+
+        Ok(Self {
+            path: path.as_ref().to_path_buf(),
+            mode: CassetteMode::Replay,
+            state: Mutex::new(CassetteState {
+                interactions: file.interactions,
+                used,
+            }),
+        })
+    }
+
+    pub fn open(path: impl AsRef<Path>, mode: CassetteMode) -> Result<Self> {
+        match mode {
+            CassetteMode::Record => Self::record(path),
+            CassetteMode::Replay => Self::replay(path),
+        }
+    }
+
+    pub fn mode(&self) -> CassetteMode {
+        self.mode
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn interactions(&self) -> Vec<Interaction> {
+        self.state.lock().unwrap().interactions.clone()
+    }
+
+    /// 记录一次交互并立即写回文件
+    pub fn record_interaction<Req, Resp>(
+        &self,
+        kind: &str,
+        request: &Req,
+        response: &Resp,
+    ) -> Result<()>
+    where
+        Req: Serialize,
+        Resp: Serialize,
+    {
+        let request = serde_json::to_value(request)?;
+        let interaction = Interaction {
+            kind: kind.to_string(),
+            hash: request_hash(kind, &request),
+            request,
+            response: serde_json::to_value(response)?,
+        };
+
+        {
+            let mut state = self.state.lock().unwrap();
+            state.interactions.push(interaction);
+            state.used.push(false);
+        }
+
+        self.save()
+    }
+
+    /// 按请求哈希取出尚未使用过的响应
+    pub fn replay_interaction<Req, Resp>(&self, kind: &str, request: &Req) -> Result<Resp>
+    where
+        Req: Serialize,
+        Resp: DeserializeOwned,
+    {
+        let request = serde_json::to_value(request)?;
+        let hash = request_hash(kind, &request);
+
+        let mut state = self.state.lock().unwrap();
+        let CassetteState { interactions, used } = &mut *state;
+
+        let index = interactions
+            .iter()
+            .zip(used.iter())
+            .position(|(interaction, used)| {
+                !used && interaction.kind == kind && interaction.hash == hash
+            })
+            .ok_or_else(|| {
+                anyhow!(
+                    "No recorded {} interaction matches request {} in cassette {}",
+                    kind,
+                    hash,
+                    self.path.display()
+                )
+            })?;
+
+        used[index] = true;
+
+        Ok(serde_json::from_value(
+            interactions[index].response.clone(),
+        )?)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let file = CassetteFile {
+            interactions: self.interactions(),
+        };
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(&file)?)?;
+
+        Ok(())
+    }
+}
+
+/// 请求哈希：对请求类型和规范化后的 JSON（键已排序）做 FNV-1a，保证跨进程、跨版本稳定
+pub(crate) fn request_hash(kind: &str, request: &Value) -> String {
+    format!("{:016x}", fnv1a(&format!("{}:{}", kind, request)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn cassette_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("my-agent-{}-{}.json", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_cassette_mode_from_str() -> Result<()> {
+        assert_eq!("record".parse::<CassetteMode>()?, CassetteMode::Record);
+        assert_eq!("replay".parse::<CassetteMode>()?, CassetteMode::Replay);
+        assert!("invalid".parse::<CassetteMode>().is_err());
+        assert_eq!(CassetteMode::Replay.to_string(), "replay");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_request_hash_is_stable() {
+        let a = json!({"b": 1, "a": [1, 2]});
+        let b = json!({"a": [1, 2], "b": 1});
+
+        assert_eq!(request_hash("chat", &a), request_hash("chat", &b));
+        assert_ne!(request_hash("chat", &a), request_hash("tavily", &a));
+        assert_eq!(request_hash("chat", &a).len(), 16);
+    }
+
+    #[test]
+    fn test_cassette_record_and_replay() -> Result<()> {
+        let path = cassette_path("cassette-record-replay");
+
+        let cassette = Cassette::record(&path)?;
+        cassette.record_interaction("chat", &json!({"q": 1}), &json!("first"))?;
+        cassette.record_interaction("chat", &json!({"q": 1}), &json!("second"))?;
+        cassette.record_interaction("tavily", &json!({"q": 2}), &json!("search"))?;
+
+        let cassette = Cassette::replay(&path)?;
+        assert_eq!(cassette.mode(), CassetteMode::Replay);
+        assert_eq!(cassette.interactions().len(), 3);
+
+        // 相同请求按录制顺序回放
+        let first: String = cassette.replay_interaction("chat", &json!({"q": 1}))?;
+        let second: String = cassette.replay_interaction("chat", &json!({"q": 1}))?;
+        let search: String = cassette.replay_interaction("tavily", &json!({"q": 2}))?;
+        assert_eq!(first, "first");
+        assert_eq!(second, "second");
+        assert_eq!(search, "search");
+
+        assert!(cassette
+            .replay_interaction::<_, String>("chat", &json!({"q": 1}))
+            .is_err());
+        assert!(cassette
+            .replay_interaction::<_, String>("chat", &json!({"q": 3}))
+            .is_err());
+
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,4 @@
+#[allow(clippy::module_inception)]
+mod cassette;
+
+pub use cassette::{Cassette, CassetteMode, Interaction};
@@ -0,0 +1,205 @@
+use super::RunRecord;
+use crate::planning::Planning;
+use anyhow::{anyhow, Result};
+use std::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+/// 运行报告的格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Markdown,
+    /// 不依赖外部资源的单个 HTML 文件
+    Html,
+}
+
+impl ReportFormat {
+    /// 报告文件的扩展名
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ReportFormat::Markdown => "md",
+            ReportFormat::Html => "html",
+        }
+    }
+}
+
+impl FromStr for ReportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "markdown" | "md" => Ok(ReportFormat::Markdown),
+            "html" => Ok(ReportFormat::Html),
+            _ => Err(anyhow!("Invalid report format")),
+        }
+    }
+}
+
+impl Display for ReportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReportFormat::Markdown => write!(f, "markdown"),
+            ReportFormat::Html => write!(f, "html"),
+        }
+    }
+}
+
+impl RunRecord {
+    /// 将运行记录导出为便于分享的报告，包含问题、每一步的思考、工具调用、搜索来源和最终答案
+    pub fn export(&self, format: ReportFormat) -> Result<String> {
+        Planning::try_new()?.build_report(self, format)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agent::{ReActAgentConfig, RunStatus, RunUsage},
+        history::ToolCallRecord,
+        tools::ToolSource,
+    };
+    use async_openai::types::{
+        ChatCompletionMessageToolCall, ChatCompletionRequestAssistantMessageArgs,
+        ChatCompletionRequestSystemMessageArgs, ChatCompletionRequestToolMessageArgs,
+        ChatCompletionToolType, FunctionCall,
+    };
+
+    fn tool_call(id: &str, name: &str, arguments: &str) -> ChatCompletionMessageToolCall {
+        ChatCompletionMessageToolCall {
+            id: id.to_string(),
+            r#type: ChatCompletionToolType::Function,
+            function: FunctionCall {
+                name: name.to_string(),
+                arguments: arguments.to_string(),
+            },
+        }
+    }
+
+    fn record() -> Result<RunRecord> {
+        let config = ReActAgentConfig::builder()
+            .set_api_key("my_api_key")
+            .set_model("mock-model")
+            .try_set_base_url("http://localhost")?
+            .build()?;
+
+        Ok(RunRecord {
+            id: 1,
+            question: "Context Caching 是什么？".to_string(),
+            config,
+            status: RunStatus::Completed,
+            answer: Some("一种 <缓存> 技术".to_string()),
+            error: None,
+            started_at: 1_700_000_000_000,
+            finished_at: 1_700_000_003_500,
+            usage: RunUsage::default(),
+            messages: vec![
+                ChatCompletionRequestSystemMessageArgs::default()
+                    .content("系统提示词")
+                    .build()?
+                    .into(),
+                ChatCompletionRequestAssistantMessageArgs::default()
+                    .content("需要先搜索")
+                    .tool_calls(vec![tool_call(
+                        "call_1",
+                        "search",
+                        r#"{"query":"Context Caching"}"#,
+                    )])
+                    .build()?
+                    .into(),
+                ChatCompletionRequestToolMessageArgs::default()
+                    .tool_call_id("call_1")
+                    .content("缓存技术\n")
+                    .build()?
+                    .into(),
+                ChatCompletionRequestAssistantMessageArgs::default()
+                    .tool_calls(vec![tool_call(
+                        "call_2",
+                        "finish",
+                        r#"{"result":"一种 <缓存> 技术"}"#,
+                    )])
+                    .build()?
+                    .into(),
+                ChatCompletionRequestToolMessageArgs::default()
+                    .tool_call_id("call_2")
+                    .content("一种 <缓存> 技术")
+                    .build()?
+                    .into(),
+            ],
+            tool_calls: vec![ToolCallRecord {
+                id: "call_1".to_string(),
+                name: "search".to_string(),
+                arguments: r#"{"query":"Context Caching"}"#.to_string(),
+                result: Some("缓存技术".to_string()),
+                error: None,
+                started_at: 1_700_000_001_000,
+                finished_at: Some(1_700_000_002_200),
+                sources: vec![ToolSource {
+                    title: "Kimi [文档]".to_string(),
+                    url: "https://example.com/docs".to_string(),
+                }],
+            }],
+        })
+    }
+
+    #[test]
+    fn test_export_markdown() -> Result<()> {
+        let report = record()?.export(ReportFormat::Markdown)?;
+
+        assert!(report.starts_with("# Context Caching 是什么？\n"));
+        assert!(report.contains("- 状态: 已完成\n"));
+        assert!(report.contains("耗时 3.5 秒"));
+        assert!(!report.contains("系统提示词"));
+        assert!(report.contains("### 第 1 步\n\n需要先搜索\n"));
+        assert!(report.contains("<summary>调用工具 <code>search</code>（1.2 秒）</summary>"));
+        assert!(report.contains("\"query\": \"Context Caching\""));
+        assert!(report.contains("- [Kimi \\[文档\\]](https://example.com/docs)"));
+        assert!(report.contains("### 第 2 步"));
+        assert!(report.ends_with("## 最终答案\n\n一种 <缓存> 技术\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_html() -> Result<()> {
+        let report = record()?.export(ReportFormat::Html)?;
+
+        assert!(report.starts_with("<!DOCTYPE html>"));
+        assert!(report.contains("<details>"));
+        assert!(report.contains(">Kimi [文档]</a>"));
+        // 内容经过转义，不会破坏页面结构
+        assert!(report.contains("一种 &lt;缓存&gt; 技术"));
+        assert!(!report.contains("一种 <缓存> 技术"));
+
+        assert_eq!("md".parse::<ReportFormat>()?, ReportFormat::Markdown);
+        assert!("pdf".parse::<ReportFormat>().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_untrusted_content() -> Result<()> {
+        let mut record = record()?;
+        record.messages[2] = ChatCompletionRequestToolMessageArgs::default()
+            .tool_call_id("call_1")
+            .content("```rust\nfn main() {}\n```")
+            .build()?
+            .into();
+        record.tool_calls[0].sources.push(ToolSource {
+            title: "恶意链接".to_string(),
+            url: "javascript:alert(1)".to_string(),
+        });
+
+        // 结果中的代码块不会提前结束报告中的代码块
+        let report = record.export(ReportFormat::Markdown)?;
+        assert!(report.contains("````\n```rust\nfn main() {}\n```\n````"));
+        assert!(!report.contains("恶意链接"));
+
+        let report = record.export(ReportFormat::Html)?;
+        assert!(report.contains(">Kimi [文档]</a>"));
+        assert!(!report.contains("javascript:"));
+
+        Ok(())
+    }
+}
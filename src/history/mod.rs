@@ -0,0 +1,7 @@
+mod export;
+mod recorder;
+mod run_history;
+
+pub use export::ReportFormat;
+pub(crate) use recorder::RunRecorder;
+pub use run_history::{RunHistory, RunRecord, RunSummary, ToolCallRecord};
@@ -0,0 +1,98 @@
+use super::{RunRecord, ToolCallRecord};
+use crate::agent::{AgentEvent, ReActAgentConfig, RunStatus, RunUsage};
+use anyhow::Result;
+use async_openai::types::ChatCompletionRequestMessage;
+use chrono::Utc;
+
+/// 根据运行中产生的事件整理运行记录
+pub(crate) struct RunRecorder {
+    record: RunRecord,
+}
+
+impl RunRecorder {
+    pub(crate) fn new(question: &str, config: &ReActAgentConfig) -> Self {
+        let record = RunRecord {
+            id: 0,
+            question: question.to_string(),
+            config: config.clone(),
+            status: RunStatus::Error,
+            answer: None,
+            error: None,
+            started_at: Utc::now().timestamp_millis(),
+            finished_at: 0,
+            usage: RunUsage::default(),
+            messages: Vec::new(),
+            tool_calls: Vec::new(),
+        };
+
+        Self { record }
+    }
+
+    pub(crate) fn observe(&mut self, event: &Result<AgentEvent>) {
+        let now = Utc::now().timestamp_millis();
+        let record = &mut self.record;
+
+        match event {
+            Ok(AgentEvent::ToolCallStarted {
+                id,
+                name,
+                arguments,
+            }) => record.tool_calls.push(ToolCallRecord {
+                id: id.clone(),
+                name: name.clone(),
+                arguments: arguments.clone(),
+                result: None,
+                error: None,
+                started_at: now,
+                finished_at: None,
+                sources: Vec::new(),
+            }),
+            Ok(AgentEvent::ToolCallFinished {
+                id,
+                result,
+                sources,
+                ..
+            }) => {
+                if let Some(call) = self.find_call(id) {
+                    call.result = Some(result.clone());
+                    call.sources = sources.clone();
+                    call.finished_at = Some(now);
+                }
+            }
+            Ok(AgentEvent::ToolFailed { id, error, .. }) => {
+                if let Some(call) = self.find_call(id) {
+                    call.error = Some(error.clone());
+                    call.finished_at = Some(now);
+                }
+            }
+            Ok(AgentEvent::FinalAnswer { answer }) => record.answer = Some(answer.clone()),
+            Ok(_) => {}
+            Err(e) => {
+                record.error.get_or_insert_with(|| format!("{:#}", e));
+            }
+        }
+    }
+
+    /// messages 为本次运行的完整对话记录，usage 为包含总结和收尾请求在内的累计用量
+    pub(crate) fn finish(
+        mut self,
+        status: RunStatus,
+        messages: Vec<ChatCompletionRequestMessage>,
+        usage: RunUsage,
+    ) -> RunRecord {
+        self.record.status = status;
+        self.record.messages = messages;
+        self.record.usage = usage;
+        self.record.finished_at = Utc::now().timestamp_millis();
+        self.record
+    }
+
+    // 同一个 id 的工具调用可能在恢复运行时再次执行，取最近的一次
+    fn find_call(&mut self, id: &str) -> Option<&mut ToolCallRecord> {
+        self.record
+            .tool_calls
+            .iter_mut()
+            .rev()
+            .find(|call| call.id == id)
+    }
+}
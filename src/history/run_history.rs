@@ -0,0 +1,405 @@
+use crate::{
+    agent::{ReActAgentConfig, RunStatus, RunUsage},
+    tools::ToolSource,
+};
+use anyhow::{Context, Result};
+use async_openai::types::ChatCompletionRequestMessage;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::{path::Path, sync::Mutex};
+
+const SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    question TEXT NOT NULL,
+    config TEXT NOT NULL,
+    status TEXT NOT NULL,
+    answer TEXT,
+    error TEXT,
+    started_at INTEGER NOT NULL,
+    finished_at INTEGER NOT NULL,
+    prompt_tokens INTEGER NOT NULL,
+    completion_tokens INTEGER NOT NULL,
+    cost REAL NOT NULL
+);
+CREATE TABLE IF NOT EXISTS messages (
+    run_id INTEGER NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    message TEXT NOT NULL,
+    PRIMARY KEY (run_id, position)
+);
+CREATE TABLE IF NOT EXISTS tool_calls (
+    run_id INTEGER NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    call_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    arguments TEXT NOT NULL,
+    result TEXT,
+    error TEXT,
+    started_at INTEGER NOT NULL,
+    finished_at INTEGER,
+    sources TEXT NOT NULL DEFAULT '[]',
+    PRIMARY KEY (run_id, position)
+);
+"#;
+
+/// 运行记录的概要，用于列出历史运行
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunSummary {
+    pub id: i64,
+    pub question: String,
+    pub status: RunStatus,
+    // Unix 时间戳（毫秒）
+    pub started_at: i64,
+    pub finished_at: i64,
+    pub usage: RunUsage,
+}
+
+/// 一次运行的完整记录
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunRecord {
+    // 写入数据库时分配，写入前为 0
+    pub id: i64,
+    pub question: String,
+    pub config: ReActAgentConfig,
+    pub status: RunStatus,
+    // 最终答案，未给出答案时为 None
+    pub answer: Option<String>,
+    // 运行出错时的错误信息
+    pub error: Option<String>,
+    // Unix 时间戳（毫秒）
+    pub started_at: i64,
+    pub finished_at: i64,
+    pub usage: RunUsage,
+    // 本次运行的全部消息，包含系统消息和已被移出短期记忆的消息
+    pub messages: Vec<ChatCompletionRequestMessage>,
+    pub tool_calls: Vec<ToolCallRecord>,
+}
+
+/// 一次工具调用的参数、结果和耗时
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCallRecord {
+    pub id: String,
+    pub name: String,
+    pub arguments: String,
+    pub result: Option<String>,
+    pub error: Option<String>,
+    // Unix 时间戳（毫秒），调用未结束时 finished_at 为 None
+    pub started_at: i64,
+    pub finished_at: Option<i64>,
+    // 结果引用的来源，例如搜索到的网页
+    pub sources: Vec<ToolSource>,
+}
+
+/// 保存在本地 SQLite 数据库中的运行记录，用于审计和分析失败的运行
+pub struct RunHistory {
+    connection: Mutex<Connection>,
+}
+
+impl RunHistory {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let connection = Connection::open(path)
+            .with_context(|| format!("打开运行记录数据库失败: {}", path.display()))?;
+
+        Self::with_connection(connection)
+    }
+
+    /// 不写入文件的数据库，进程退出后记录丢失
+    pub fn open_in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self> {
+        connection.pragma_update(None, "foreign_keys", true)?;
+        connection.execute_batch(SCHEMA)?;
+
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    /// 写入一次运行的记录，返回分配的编号
+    pub fn insert(&self, record: &RunRecord) -> Result<i64> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+
+        transaction.execute(
+            "INSERT INTO runs (question, config, status, answer, error, started_at, finished_at,
+                prompt_tokens, completion_tokens, cost)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                record.question,
+                serde_json::to_string(&record.config)?,
+                status_to_sql(record.status)?,
+                record.answer,
+                record.error,
+                record.started_at,
+                record.finished_at,
+                record.usage.prompt_tokens,
+                record.usage.completion_tokens,
+                record.usage.cost,
+            ],
+        )?;
+        let id = transaction.last_insert_rowid();
+
+        for (position, message) in record.messages.iter().enumerate() {
+            transaction.execute(
+                "INSERT INTO messages (run_id, position, message) VALUES (?1, ?2, ?3)",
+                params![id, position, serde_json::to_string(message)?],
+            )?;
+        }
+
+        for (position, call) in record.tool_calls.iter().enumerate() {
+            transaction.execute(
+                "INSERT INTO tool_calls (run_id, position, call_id, name, arguments, result, error,
+                    started_at, finished_at, sources)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                params![
+                    id,
+                    position,
+                    call.id,
+                    call.name,
+                    call.arguments,
+                    call.result,
+                    call.error,
+                    call.started_at,
+                    call.finished_at,
+                    serde_json::to_string(&call.sources)?,
+                ],
+            )?;
+        }
+
+        transaction.commit()?;
+
+        Ok(id)
+    }
+
+    /// 按开始时间从新到旧列出最近的 limit 次运行
+    pub fn list(&self, limit: usize) -> Result<Vec<RunSummary>> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection.prepare(
+            "SELECT id, question, status, started_at, finished_at, prompt_tokens,
+                completion_tokens, cost
+             FROM runs ORDER BY started_at DESC, id DESC LIMIT ?1",
+        )?;
+
+        let rows = statement.query_map(params![limit], |row| {
+            Ok((
+                RunSummary {
+                    id: row.get(0)?,
+                    question: row.get(1)?,
+                    status: RunStatus::Error,
+                    started_at: row.get(3)?,
+                    finished_at: row.get(4)?,
+                    usage: RunUsage {
+                        prompt_tokens: row.get(5)?,
+                        completion_tokens: row.get(6)?,
+                        cost: row.get(7)?,
+                    },
+                },
+                row.get::<_, String>(2)?,
+            ))
+        })?;
+
+        rows.map(|row| {
+            let (mut summary, status) = row?;
+            summary.status = status_from_sql(status)?;
+            Ok(summary)
+        })
+        .collect()
+    }
+
+    /// 读取一次运行的完整记录，不存在时返回 None
+    pub fn fetch(&self, id: i64) -> Result<Option<RunRecord>> {
+        let connection = self.connection.lock().unwrap();
+
+        let run = connection
+            .query_row(
+                "SELECT question, config, status, answer, error, started_at, finished_at,
+                    prompt_tokens, completion_tokens, cost
+                 FROM runs WHERE id = ?1",
+                params![id],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, Option<String>>(3)?,
+                        row.get::<_, Option<String>>(4)?,
+                        row.get::<_, i64>(5)?,
+                        row.get::<_, i64>(6)?,
+                        RunUsage {
+                            prompt_tokens: row.get(7)?,
+                            completion_tokens: row.get(8)?,
+                            cost: row.get(9)?,
+                        },
+                    ))
+                },
+            )
+            .optional()?;
+
+        let Some((question, config, status, answer, error, started_at, finished_at, usage)) = run
+        else {
+            return Ok(None);
+        };
+
+        let messages = connection
+            .prepare("SELECT message FROM messages WHERE run_id = ?1 ORDER BY position")?
+            .query_map(params![id], |row| row.get::<_, String>(0))?
+            .map(|message| Ok(serde_json::from_str(&message?)?))
+            .collect::<Result<Vec<_>>>()?;
+
+        let tool_calls = connection
+            .prepare(
+                "SELECT call_id, name, arguments, result, error, started_at, finished_at, sources
+                 FROM tool_calls WHERE run_id = ?1 ORDER BY position",
+            )?
+            .query_map(params![id], |row| {
+                Ok((
+                    ToolCallRecord {
+                        id: row.get(0)?,
+                        name: row.get(1)?,
+                        arguments: row.get(2)?,
+                        result: row.get(3)?,
+                        error: row.get(4)?,
+                        started_at: row.get(5)?,
+                        finished_at: row.get(6)?,
+                        sources: Vec::new(),
+                    },
+                    row.get::<_, String>(7)?,
+                ))
+            })?
+            .map(|row| {
+                let (mut call, sources) = row?;
+                call.sources = serde_json::from_str(&sources)?;
+                Ok(call)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Some(RunRecord {
+            id,
+            question,
+            config: serde_json::from_str(&config)?,
+            status: status_from_sql(status)?,
+            answer,
+            error,
+            started_at,
+            finished_at,
+            usage,
+            messages,
+            tool_calls,
+        }))
+    }
+
+    /// 删除一次运行的记录，返回记录是否存在
+    pub fn delete(&self, id: i64) -> Result<bool> {
+        let connection = self.connection.lock().unwrap();
+        let deleted = connection.execute("DELETE FROM runs WHERE id = ?1", params![id])?;
+
+        Ok(deleted > 0)
+    }
+}
+
+// 结束原因以 completed 等文本保存，便于直接用 SQL 筛选失败的运行
+fn status_to_sql(status: RunStatus) -> Result<String> {
+    Ok(serde_json::to_value(status)?
+        .as_str()
+        .unwrap_or_default()
+        .to_string())
+}
+
+fn status_from_sql(status: String) -> Result<RunStatus> {
+    Ok(serde_json::from_value(serde_json::Value::String(status))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_openai::types::ChatCompletionRequestUserMessageArgs;
+
+    fn record(question: &str, started_at: i64) -> Result<RunRecord> {
+        let config = ReActAgentConfig::builder()
+            .set_api_key("my_api_key")
+            .set_model("mock-model")
+            .try_set_base_url("http://localhost")?
+            .build()?;
+
+        Ok(RunRecord {
+            id: 0,
+            question: question.to_string(),
+            config,
+            status: RunStatus::Completed,
+            answer: Some("42".to_string()),
+            error: None,
+            started_at,
+            finished_at: started_at + 1_000,
+            usage: RunUsage {
+                prompt_tokens: 100,
+                completion_tokens: 20,
+                cost: 0.5,
+            },
+            messages: vec![ChatCompletionRequestUserMessageArgs::default()
+                .content(question)
+                .build()?
+                .into()],
+            tool_calls: vec![ToolCallRecord {
+                id: "call_1".to_string(),
+                name: "search".to_string(),
+                arguments: r#"{"query":"42"}"#.to_string(),
+                result: None,
+                error: Some("timeout".to_string()),
+                started_at,
+                finished_at: Some(started_at + 500),
+                sources: vec![ToolSource {
+                    title: "[42] 终极答案".to_string(),
+                    url: "https://example.com/42".to_string(),
+                }],
+            }],
+        })
+    }
+
+    #[test]
+    fn test_run_history() -> Result<()> {
+        let history = RunHistory::open_in_memory()?;
+
+        let first = history.insert(&record("第一个问题", 1_000)?)?;
+        let second = history.insert(&record("第二个问题", 2_000)?)?;
+
+        let runs = history.list(10)?;
+        assert_eq!(
+            runs.iter().map(|run| run.id).collect::<Vec<_>>(),
+            vec![second, first]
+        );
+        assert_eq!(runs[0].question, "第二个问题");
+        assert_eq!(runs[0].status, RunStatus::Completed);
+        assert_eq!(runs[0].usage.prompt_tokens, 100);
+        assert_eq!(history.list(1)?.len(), 1);
+
+        let fetched = history.fetch(first)?.unwrap();
+        assert_eq!(
+            fetched,
+            RunRecord {
+                id: first,
+                // 密钥不写入数据库
+                config: ReActAgentConfig {
+                    api_key: String::new(),
+                    ..fetched.config.clone()
+                },
+                ..record("第一个问题", 1_000)?
+            }
+        );
+        assert_eq!(fetched.config.api_key, "");
+
+        assert!(history.delete(first)?);
+        assert!(!history.delete(first)?);
+        assert_eq!(history.fetch(first)?, None);
+        assert_eq!(history.list(10)?.len(), 1);
+
+        Ok(())
+    }
+}
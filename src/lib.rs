@@ -1,4 +1,11 @@
+// 派生宏生成的代码通过 ::my_agent 引用本 crate
+extern crate self as my_agent;
+
 pub mod agent;
+pub mod cassette;
+pub mod history;
+pub mod llm;
 pub mod memory;
 pub mod planning;
 pub mod tools;
+mod utils;
@@ -0,0 +1,143 @@
+use super::{ChatBackend, ChatStream};
+use crate::cassette::{Cassette, CassetteMode};
+use anyhow::Result;
+use async_openai::types::{
+    CreateChatCompletionRequest, CreateChatCompletionResponse, CreateChatCompletionStreamResponse,
+};
+use futures::TryStreamExt;
+use std::sync::Arc;
+
+const CASSETTE_KIND: &str = "chat";
+const CASSETTE_STREAM_KIND: &str = "chat_stream";
+
+/// 为任意后端加上录制/回放能力
+///
+/// 录制模式下转发给内部后端并写入磁带，回放模式下直接从磁带中取出响应，不会调用内部后端
+#[derive(Debug, Clone)]
+pub struct CassetteBackend<B> {
+    inner: B,
+    cassette: Arc<Cassette>,
+}
+
+impl<B> CassetteBackend<B> {
+    pub fn new(inner: B, cassette: Arc<Cassette>) -> Self {
+        Self { inner, cassette }
+    }
+
+    pub fn cassette(&self) -> &Arc<Cassette> {
+        &self.cassette
+    }
+}
+
+impl<B: ChatBackend> ChatBackend for CassetteBackend<B> {
+    async fn chat(
+        &self,
+        request: CreateChatCompletionRequest,
+    ) -> Result<CreateChatCompletionResponse> {
+        match self.cassette.mode() {
+            CassetteMode::Replay => self.cassette.replay_interaction(CASSETTE_KIND, &request),
+            CassetteMode::Record => {
+                let response = self.inner.chat(request.clone()).await?;
+                self.cassette
+                    .record_interaction(CASSETTE_KIND, &request, &response)?;
+                Ok(response)
+            }
+        }
+    }
+
+    /// 流式请求以分片列表的形式录制，回放时按原样逐个返回
+    async fn chat_stream(&self, request: CreateChatCompletionRequest) -> Result<ChatStream> {
+        let chunks: Vec<CreateChatCompletionStreamResponse> = match self.cassette.mode() {
+            CassetteMode::Replay => self
+                .cassette
+                .replay_interaction(CASSETTE_STREAM_KIND, &request)?,
+            CassetteMode::Record => {
+                let chunks = self
+                    .inner
+                    .chat_stream(request.clone())
+                    .await?
+                    .try_collect::<Vec<_>>()
+                    .await?;
+                self.cassette
+                    .record_interaction(CASSETTE_STREAM_KIND, &request, &chunks)?;
+                chunks
+            }
+        };
+
+        Ok(Box::pin(futures::stream::iter(chunks.into_iter().map(Ok))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{response_into_chunks, MockBackend};
+    use async_openai::types::CreateChatCompletionRequestArgs;
+
+    #[tokio::test]
+    async fn test_cassette_backend_record_and_replay() -> Result<()> {
+        let path = std::env::temp_dir().join(format!(
+            "my-agent-cassette-backend-{}.json",
+            std::process::id()
+        ));
+
+        let request = CreateChatCompletionRequestArgs::default()
+            .model("mock")
+            .build()?;
+
+        let recorder = CassetteBackend::new(
+            MockBackend::with_responses([MockBackend::text_response("recorded")]),
+            Arc::new(Cassette::record(&path)?),
+        );
+        let recorded = recorder.chat(request.clone()).await?;
+
+        // 回放时内部后端没有任何脚本，响应只能来自磁带
+        let replayer = CassetteBackend::new(MockBackend::new(), Arc::new(Cassette::replay(&path)?));
+        let replayed = replayer.chat(request.clone()).await?;
+
+        assert_eq!(recorded, replayed);
+        assert!(replayer.inner.requests().is_empty());
+        assert!(replayer.chat(request).await.is_err());
+
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cassette_backend_record_and_replay_stream() -> Result<()> {
+        let path = std::env::temp_dir().join(format!(
+            "my-agent-cassette-backend-stream-{}.json",
+            std::process::id()
+        ));
+
+        let request = CreateChatCompletionRequestArgs::default()
+            .model("mock")
+            .stream(true)
+            .build()?;
+
+        let inner = MockBackend::new();
+        inner.push_chunks(response_into_chunks(
+            MockBackend::text_response("recorded"),
+            Some(3),
+        ));
+        let recorder = CassetteBackend::new(inner, Arc::new(Cassette::record(&path)?));
+        let recorded = recorder
+            .chat_stream(request.clone())
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?;
+
+        let replayer = CassetteBackend::new(MockBackend::new(), Arc::new(Cassette::replay(&path)?));
+        let replayed = replayer
+            .chat_stream(request)
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?;
+
+        assert_eq!(recorded.len(), 4);
+        assert_eq!(recorded, replayed);
+
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+}
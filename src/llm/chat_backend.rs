@@ -0,0 +1,30 @@
+use super::{chat_stream::response_into_chunks, ChatStream};
+use anyhow::Result;
+use async_openai::types::{CreateChatCompletionRequest, CreateChatCompletionResponse};
+use std::future::Future;
+
+/// 大模型后端：输入对话请求，输出模型的回复（文本内容或工具调用）
+///
+/// ReActAgent 和 Planning 只依赖这个 trait，OpenAI 兼容接口只是其中一种实现，
+/// 切换厂商或在测试中注入替身时不需要改动 Agent 的主循环
+pub trait ChatBackend: Send + Sync {
+    fn chat(
+        &self,
+        request: CreateChatCompletionRequest,
+    ) -> impl Future<Output = Result<CreateChatCompletionResponse>> + Send;
+
+    /// 流式请求，逐个返回回复分片
+    ///
+    /// 默认实现退化为一次完整请求，再把回复作为分片返回，不支持流式接口的后端无需实现
+    fn chat_stream(
+        &self,
+        request: CreateChatCompletionRequest,
+    ) -> impl Future<Output = Result<ChatStream>> + Send {
+        async move {
+            let response = self.chat(request).await?;
+            let chunks = response_into_chunks(response, None);
+
+            Ok(Box::pin(futures::stream::iter(chunks.into_iter().map(Ok))) as ChatStream)
+        }
+    }
+}
@@ -0,0 +1,306 @@
+use anyhow::Result;
+use async_openai::types::{
+    ChatChoice, ChatChoiceStream, ChatCompletionMessageToolCall,
+    ChatCompletionMessageToolCallChunk, ChatCompletionResponseMessage,
+    ChatCompletionStreamResponseDelta, ChatCompletionToolType, CompletionUsage,
+    CreateChatCompletionResponse, CreateChatCompletionStreamResponse, FinishReason, FunctionCall,
+    FunctionCallStream, Role,
+};
+use futures::Stream;
+use std::pin::Pin;
+
+pub type ChatStream =
+    Pin<Box<dyn Stream<Item = Result<CreateChatCompletionStreamResponse>> + Send>>;
+
+/// 将流式返回的分片拼装成完整的回复
+///
+/// 工具调用的参数会被拆分到多个分片中，需要按 index 依次拼接，
+/// 拼装完成后得到的回复与非流式接口的返回一致，可以直接放入短期记忆
+#[derive(Debug, Default)]
+pub struct ChatStreamAssembler {
+    id: String,
+    created: u32,
+    model: String,
+    system_fingerprint: Option<String>,
+    content: Option<String>,
+    tool_calls: Vec<ChatCompletionMessageToolCall>,
+    finish_reason: Option<FinishReason>,
+    usage: Option<CompletionUsage>,
+    // 是否收到过第一个候选回复的分片
+    has_choice: bool,
+}
+
+impl ChatStreamAssembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 合并一个分片，返回其中新增的文本内容
+    pub fn push(&mut self, chunk: CreateChatCompletionStreamResponse) -> Option<String> {
+        if self.id.is_empty() {
+            self.id = chunk.id;
+            self.created = chunk.created;
+            self.model = chunk.model;
+            self.system_fingerprint = chunk.system_fingerprint;
+        }
+
+        if chunk.usage.is_some() {
+            self.usage = chunk.usage;
+        }
+
+        // 只拼装第一个候选回复
+        let choice = chunk.choices.into_iter().find(|choice| choice.index == 0)?;
+        self.has_choice = true;
+
+        if choice.finish_reason.is_some() {
+            self.finish_reason = choice.finish_reason;
+        }
+
+        for tool_call in choice.delta.tool_calls.unwrap_or_default() {
+            self.push_tool_call(tool_call);
+        }
+
+        let delta = choice.delta.content?;
+        self.content
+            .get_or_insert_with(String::new)
+            .push_str(&delta);
+
+        Some(delta).filter(|delta| !delta.is_empty())
+    }
+
+    fn push_tool_call(&mut self, chunk: ChatCompletionMessageToolCallChunk) {
+        let index = chunk.index.max(0) as usize;
+
+        while self.tool_calls.len() <= index {
+            self.tool_calls.push(ChatCompletionMessageToolCall {
+                id: String::new(),
+                r#type: ChatCompletionToolType::Function,
+                function: FunctionCall {
+                    name: String::new(),
+                    arguments: String::new(),
+                },
+            });
+        }
+
+        let tool_call = &mut self.tool_calls[index];
+
+        if let Some(id) = chunk.id.filter(|id| !id.is_empty()) {
+            tool_call.id = id;
+        }
+
+        if let Some(function) = chunk.function {
+            // 名称只在第一个分片中完整给出，部分厂商会在后续分片中重复发送
+            if let Some(name) = function.name.filter(|_| tool_call.function.name.is_empty()) {
+                tool_call.function.name = name;
+            }
+            if let Some(arguments) = function.arguments {
+                tool_call.function.arguments.push_str(&arguments);
+            }
+        }
+    }
+
+    /// 没有收到任何候选回复时 choices 为空，与非流式接口一致
+    #[allow(deprecated)]
+    pub fn finish(self) -> CreateChatCompletionResponse {
+        let tool_calls = Some(self.tool_calls).filter(|tool_calls| !tool_calls.is_empty());
+        let choices = self.has_choice.then_some(ChatChoice {
+            index: 0,
+            message: ChatCompletionResponseMessage {
+                content: self.content,
+                tool_calls,
+                role: Role::Assistant,
+                function_call: None,
+            },
+            finish_reason: self.finish_reason,
+            logprobs: None,
+        });
+
+        CreateChatCompletionResponse {
+            id: self.id,
+            choices: choices.into_iter().collect(),
+            created: self.created,
+            model: self.model,
+            system_fingerprint: self.system_fingerprint,
+            object: "chat.completion".to_string(),
+            usage: self.usage,
+        }
+    }
+}
+
+/// 将完整的回复拆分成流式分片，文本内容和工具参数按 chunk_chars 个字符切分
+///
+/// chunk_chars 为 None 时不切分，每个候选回复只生成一个分片
+#[allow(deprecated)]
+pub fn response_into_chunks(
+    response: CreateChatCompletionResponse,
+    chunk_chars: Option<usize>,
+) -> Vec<CreateChatCompletionStreamResponse> {
+    let chunk = |choices: Vec<ChatChoiceStream>, usage: Option<CompletionUsage>| {
+        CreateChatCompletionStreamResponse {
+            id: response.id.clone(),
+            choices,
+            created: response.created,
+            model: response.model.clone(),
+            system_fingerprint: response.system_fingerprint.clone(),
+            object: "chat.completion.chunk".to_string(),
+            usage,
+        }
+    };
+
+    let delta = |content: Option<String>,
+                 tool_calls: Option<Vec<ChatCompletionMessageToolCallChunk>>| {
+        ChatCompletionStreamResponseDelta {
+            content,
+            function_call: None,
+            tool_calls,
+            role: Some(Role::Assistant),
+        }
+    };
+
+    let mut chunks = Vec::new();
+
+    for choice in &response.choices {
+        let stream_choice = |delta, finish_reason| ChatChoiceStream {
+            index: choice.index,
+            delta,
+            finish_reason,
+            logprobs: None,
+        };
+
+        if let Some(content) = &choice.message.content {
+            for part in split(content, chunk_chars) {
+                chunks.push(chunk(
+                    vec![stream_choice(delta(Some(part), None), None)],
+                    None,
+                ));
+            }
+        }
+
+        for (index, tool_call) in choice.message.tool_calls.iter().flatten().enumerate() {
+            let arguments = split(&tool_call.function.arguments, chunk_chars);
+
+            for (position, part) in arguments.into_iter().enumerate() {
+                let first = position == 0;
+                let tool_call_chunk = ChatCompletionMessageToolCallChunk {
+                    index: index as i32,
+                    id: Some(tool_call.id.clone()).filter(|_| first),
+                    r#type: Some(ChatCompletionToolType::Function).filter(|_| first),
+                    function: Some(FunctionCallStream {
+                        name: Some(tool_call.function.name.clone()).filter(|_| first),
+                        arguments: Some(part),
+                    }),
+                };
+
+                chunks.push(chunk(
+                    vec![stream_choice(
+                        delta(None, Some(vec![tool_call_chunk])),
+                        None,
+                    )],
+                    None,
+                ));
+            }
+        }
+
+        chunks.push(chunk(
+            vec![stream_choice(delta(None, None), choice.finish_reason)],
+            None,
+        ));
+    }
+
+    if response.usage.is_some() {
+        chunks.push(chunk(vec![], response.usage.clone()));
+    }
+
+    chunks
+}
+
+fn split(content: &str, chunk_chars: Option<usize>) -> Vec<String> {
+    let chars = content.chars().collect::<Vec<_>>();
+
+    match chunk_chars {
+        Some(size) if size > 0 && !chars.is_empty() => chars
+            .chunks(size)
+            .map(|part| part.iter().collect())
+            .collect(),
+        _ => vec![content.to_string()],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::MockBackend;
+
+    #[test]
+    fn test_assemble_text_chunks() {
+        let response = MockBackend::text_response("你好，世界");
+        let chunks = response_into_chunks(response.clone(), Some(2));
+
+        let mut assembler = ChatStreamAssembler::new();
+        let deltas = chunks
+            .into_iter()
+            .filter_map(|chunk| assembler.push(chunk))
+            .collect::<Vec<_>>();
+
+        assert_eq!(deltas, vec!["你好", "，世", "界"]);
+        assert_eq!(assembler.finish(), response);
+    }
+
+    #[test]
+    fn test_assemble_tool_call_chunks() {
+        let response = MockBackend::tool_calls_response([
+            ("call_1", "search", r#"{"query":"周杰伦 年龄"}"#),
+            ("call_2", "finish", r#"{"result":"done"}"#),
+        ]);
+        let chunks = response_into_chunks(response.clone(), Some(3));
+        assert!(chunks.len() > 4);
+
+        let mut assembler = ChatStreamAssembler::new();
+        for chunk in chunks {
+            assert_eq!(assembler.push(chunk), None);
+        }
+
+        assert_eq!(assembler.finish(), response);
+    }
+
+    #[test]
+    fn test_assemble_interleaved_tool_call_chunks() {
+        let response = MockBackend::tool_calls_response([
+            ("call_1", "search", r#"{"query":"a"}"#),
+            ("call_2", "search", r#"{"query":"b"}"#),
+        ]);
+
+        // 不同工具调用的分片交错到达时，按 index 分别拼接
+        let mut chunks = response_into_chunks(response.clone(), Some(4));
+        let finish = chunks.pop().unwrap();
+        let (first, second): (Vec<_>, Vec<_>) = chunks
+            .into_iter()
+            .partition(|chunk| chunk.choices[0].delta.tool_calls.as_ref().unwrap()[0].index == 0);
+
+        let mut assembler = ChatStreamAssembler::new();
+        for (a, b) in first.into_iter().zip(second) {
+            assembler.push(a);
+            assembler.push(b);
+        }
+        assembler.push(finish);
+
+        assert_eq!(assembler.finish(), response);
+    }
+
+    #[test]
+    fn test_assemble_usage_chunk() {
+        let mut response = MockBackend::text_response("ok");
+        response.usage = Some(CompletionUsage {
+            prompt_tokens: 10,
+            completion_tokens: 2,
+            total_tokens: 12,
+        });
+
+        let mut assembler = ChatStreamAssembler::new();
+        for chunk in response_into_chunks(response.clone(), None) {
+            assembler.push(chunk);
+        }
+
+        assert_eq!(assembler.finish(), response);
+    }
+}
@@ -0,0 +1,203 @@
+use super::{response_into_chunks, ChatBackend, ChatStream, ChatStreamAssembler};
+use anyhow::{anyhow, Result};
+use async_openai::types::{
+    ChatChoice, ChatCompletionMessageToolCall, ChatCompletionResponseMessage,
+    ChatCompletionToolType, CreateChatCompletionRequest, CreateChatCompletionResponse,
+    CreateChatCompletionStreamResponse, FinishReason, FunctionCall, Role,
+};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+/// 按脚本回放的大模型后端，用于离线测试 Agent 主循环
+///
+/// 每次请求按顺序弹出一个预先准备好的回复，同时记录收到的请求，
+/// 克隆出的实例共享同一份脚本和请求记录，方便在 Agent 被消费后继续断言
+#[derive(Debug, Clone, Default)]
+pub struct MockBackend {
+    state: Arc<Mutex<MockState>>,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    responses: VecDeque<Scripted>,
+    requests: Vec<CreateChatCompletionRequest>,
+}
+
+#[derive(Debug)]
+enum Scripted {
+    Response(CreateChatCompletionResponse),
+    Chunks(Vec<CreateChatCompletionStreamResponse>),
+    Error(anyhow::Error),
+}
+
+impl MockBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_responses(
+        responses: impl IntoIterator<Item = CreateChatCompletionResponse>,
+    ) -> Self {
+        let backend = Self::new();
+        for response in responses {
+            backend.push_response(response);
+        }
+        backend
+    }
+
+    pub fn push_response(&self, response: CreateChatCompletionResponse) {
+        self.push(Scripted::Response(response));
+    }
+
+    /// 追加一组流式分片，流式请求会逐个返回这些分片，非流式请求返回拼装后的完整回复
+    pub fn push_chunks(&self, chunks: Vec<CreateChatCompletionStreamResponse>) {
+        self.push(Scripted::Chunks(chunks));
+    }
+
+    /// 追加一次失败的请求，例如 BackendError 用于测试重试策略
+    pub fn push_error(&self, error: impl Into<anyhow::Error>) {
+        self.push(Scripted::Error(error.into()));
+    }
+
+    fn push(&self, scripted: Scripted) {
+        self.state.lock().unwrap().responses.push_back(scripted);
+    }
+
+    fn next(&self, request: CreateChatCompletionRequest) -> Result<Scripted> {
+        let mut state = self.state.lock().unwrap();
+        state.requests.push(request);
+        state
+            .responses
+            .pop_front()
+            .ok_or_else(|| anyhow!("MockBackend has no scripted response left"))
+    }
+
+    /// 已收到的全部请求，按请求顺序排列
+    pub fn requests(&self) -> Vec<CreateChatCompletionRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// 尚未被消费的回复数量
+    pub fn remaining(&self) -> usize {
+        self.state.lock().unwrap().responses.len()
+    }
+
+    /// 构建一条纯文本回复
+    pub fn text_response(content: impl Into<String>) -> CreateChatCompletionResponse {
+        build_response(Some(content.into()), None, FinishReason::Stop)
+    }
+
+    /// 构建一条工具调用回复，参数为 (tool_call_id, 工具名称, JSON 参数)
+    pub fn tool_calls_response<'a>(
+        calls: impl IntoIterator<Item = (&'a str, &'a str, &'a str)>,
+    ) -> CreateChatCompletionResponse {
+        let tool_calls = calls
+            .into_iter()
+            .map(|(id, name, arguments)| ChatCompletionMessageToolCall {
+                id: id.to_string(),
+                r#type: ChatCompletionToolType::Function,
+                function: FunctionCall {
+                    name: name.to_string(),
+                    arguments: arguments.to_string(),
+                },
+            })
+            .collect();
+
+        build_response(None, Some(tool_calls), FinishReason::ToolCalls)
+    }
+}
+
+impl ChatBackend for MockBackend {
+    async fn chat(
+        &self,
+        request: CreateChatCompletionRequest,
+    ) -> Result<CreateChatCompletionResponse> {
+        match self.next(request)? {
+            Scripted::Response(response) => Ok(response),
+            Scripted::Chunks(chunks) => {
+                let mut assembler = ChatStreamAssembler::new();
+                for chunk in chunks {
+                    assembler.push(chunk);
+                }
+                Ok(assembler.finish())
+            }
+            Scripted::Error(error) => Err(error),
+        }
+    }
+
+    async fn chat_stream(&self, request: CreateChatCompletionRequest) -> Result<ChatStream> {
+        let chunks = match self.next(request)? {
+            Scripted::Response(response) => response_into_chunks(response, None),
+            Scripted::Chunks(chunks) => chunks,
+            Scripted::Error(error) => return Err(error),
+        };
+
+        Ok(Box::pin(futures::stream::iter(chunks.into_iter().map(Ok))))
+    }
+}
+
+#[allow(deprecated)]
+fn build_response(
+    content: Option<String>,
+    tool_calls: Option<Vec<ChatCompletionMessageToolCall>>,
+    finish_reason: FinishReason,
+) -> CreateChatCompletionResponse {
+    CreateChatCompletionResponse {
+        id: "mock".to_string(),
+        choices: vec![ChatChoice {
+            index: 0,
+            message: ChatCompletionResponseMessage {
+                content,
+                tool_calls,
+                role: Role::Assistant,
+                function_call: None,
+            },
+            finish_reason: Some(finish_reason),
+            logprobs: None,
+        }],
+        created: 0,
+        model: "mock".to_string(),
+        system_fingerprint: None,
+        object: "chat.completion".to_string(),
+        usage: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_openai::types::CreateChatCompletionRequestArgs;
+
+    #[tokio::test]
+    async fn test_mock_backend_replays_in_order() -> Result<()> {
+        let backend = MockBackend::with_responses([
+            MockBackend::text_response("first"),
+            MockBackend::tool_calls_response([("call_1", "finish", r#"{"result":"ok"}"#)]),
+        ]);
+
+        let request = CreateChatCompletionRequestArgs::default()
+            .model("mock")
+            .build()?;
+
+        let response = backend.chat(request.clone()).await?;
+        assert_eq!(
+            response.choices[0].message.content,
+            Some("first".to_string())
+        );
+
+        let response = backend.chat(request.clone()).await?;
+        let tool_calls = response.choices[0].message.tool_calls.clone().unwrap();
+        assert_eq!(tool_calls[0].function.name, "finish");
+
+        assert!(backend.chat(request.clone()).await.is_err());
+        assert_eq!(
+            backend.requests(),
+            vec![request.clone(), request.clone(), request]
+        );
+        assert_eq!(backend.remaining(), 0);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,13 @@
+mod cassette_backend;
+mod chat_backend;
+mod chat_stream;
+mod mock_backend;
+mod openai_backend;
+mod retry;
+
+pub use cassette_backend::CassetteBackend;
+pub use chat_backend::ChatBackend;
+pub use chat_stream::{response_into_chunks, ChatStream, ChatStreamAssembler};
+pub use mock_backend::MockBackend;
+pub use openai_backend::OpenAIBackend;
+pub use retry::{classify, BackendError, ErrorClass, RetryPolicy};
@@ -0,0 +1,225 @@
+use super::{ChatBackend, ChatStream};
+use anyhow::Result;
+use async_openai::{
+    config::OpenAIConfig,
+    types::{CreateChatCompletionRequest, CreateChatCompletionResponse},
+    Client,
+};
+use backoff::ExponentialBackoff;
+use futures::TryStreamExt;
+use std::time::Duration;
+
+/// OpenAI 兼容接口的后端实现，Moonshot、DeepSeek 等兼容厂商只需替换 base_url
+#[derive(Debug, Clone)]
+pub struct OpenAIBackend {
+    client: Client<OpenAIConfig>,
+}
+
+impl OpenAIBackend {
+    pub fn new(api_key: impl Into<String>, base_url: impl Into<String>) -> Self {
+        let openai_config = OpenAIConfig::new()
+            .with_api_key(api_key)
+            .with_api_base(base_url);
+
+        // 关闭 async-openai 内部的限流重试，由 Agent 的 RetryPolicy 统一重试
+        let no_retry = ExponentialBackoff {
+            max_elapsed_time: Some(Duration::ZERO),
+            ..Default::default()
+        };
+        let client = Client::with_config(openai_config).with_backoff(no_retry);
+
+        Self { client }
+    }
+}
+
+impl ChatBackend for OpenAIBackend {
+    async fn chat(
+        &self,
+        request: CreateChatCompletionRequest,
+    ) -> Result<CreateChatCompletionResponse> {
+        let response = self.client.chat().create(request).await?;
+        Ok(response)
+    }
+
+    async fn chat_stream(&self, request: CreateChatCompletionRequest) -> Result<ChatStream> {
+        let stream = self.client.chat().create_stream(request).await?;
+        Ok(Box::pin(stream.map_err(anyhow::Error::from)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{classify, ErrorClass};
+    use async_openai::types::{
+        ChatCompletionRequestMessage, ChatCompletionRequestUserMessageArgs,
+        CreateChatCompletionRequestArgs,
+    };
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    // 在本地启动只返回固定响应的 HTTP 服务，返回 base_url 和收到的请求数
+    async fn serve(response: String) -> anyhow::Result<(String, Arc<AtomicUsize>)> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let base_url = format!("http://{}/v1", listener.local_addr()?);
+        let requests = Arc::new(AtomicUsize::new(0));
+
+        tokio::spawn({
+            let requests = requests.clone();
+            async move {
+                while let Ok((mut socket, _)) = listener.accept().await {
+                    requests.fetch_add(1, Ordering::SeqCst);
+
+                    // 读完请求再返回，避免客户端还在发送时连接被关闭
+                    let mut request = Vec::new();
+                    let mut buffer = [0; 4096];
+                    while let Ok(n @ 1..) = socket.read(&mut buffer).await {
+                        request.extend_from_slice(&buffer[..n]);
+                        let text = String::from_utf8_lossy(&request);
+                        if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                            let length = head
+                                .lines()
+                                .find_map(|line| line.strip_prefix("content-length: "))
+                                .and_then(|length| length.parse::<usize>().ok())
+                                .unwrap_or_default();
+                            if body.len() >= length {
+                                break;
+                            }
+                        }
+                    }
+
+                    let _ = socket.write_all(response.as_bytes()).await;
+                    let _ = socket.shutdown().await;
+                }
+            }
+        });
+
+        Ok((base_url, requests))
+    }
+
+    fn http_response(status: &str, content_type: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 {}\r\ncontent-type: {}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+            status,
+            content_type,
+            body.len(),
+            body
+        )
+    }
+
+    fn request(stream: bool) -> anyhow::Result<CreateChatCompletionRequest> {
+        let message: ChatCompletionRequestMessage = ChatCompletionRequestUserMessageArgs::default()
+            .content("你好")
+            .build()?
+            .into();
+
+        let mut request = CreateChatCompletionRequestArgs::default();
+        request.model("mock-model").messages([message]);
+        if stream {
+            request.stream(true);
+        }
+        Ok(request.build()?)
+    }
+
+    // 返回请求的错误和服务端收到的请求数，流式请求的错误在第一个分片中返回
+    async fn chat_error(response: String, stream: bool) -> anyhow::Result<(anyhow::Error, usize)> {
+        let (base_url, requests) = serve(response).await?;
+        let backend = OpenAIBackend::new("my_api_key", base_url);
+
+        let error = if stream {
+            backend
+                .chat_stream(request(true)?)
+                .await?
+                .try_next()
+                .await
+                .err()
+        } else {
+            backend.chat(request(false)?).await.err()
+        };
+
+        Ok((
+            error.expect("request should fail"),
+            requests.load(Ordering::SeqCst),
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_openai_backend_does_not_retry() -> anyhow::Result<()> {
+        let body = r#"{"error":{"message":"Rate limit reached","type":"requests","param":null,"code":"rate_limit_exceeded"}}"#;
+        let response = http_response("429 Too Many Requests", "application/json", body);
+
+        // 限流错误直接返回，交给 RetryPolicy 重试
+        for stream in [false, true] {
+            let (error, requests) = chat_error(response.clone(), stream).await?;
+            assert_eq!(requests, 1);
+            assert_eq!(
+                classify(&error),
+                ErrorClass::Transient { retry_after: None }
+            );
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_openai_backend_fatal_errors() -> anyhow::Result<()> {
+        // 余额不足同样返回 429，但不可重试
+        let quota = r#"{"error":{"message":"You exceeded your current quota","type":"insufficient_quota","param":null,"code":"insufficient_quota"}}"#;
+        let auth = r#"{"error":{"message":"Invalid Authentication","type":"invalid_authentication_error"}}"#;
+
+        for (status, body, stream) in [
+            ("429 Too Many Requests", quota, false),
+            ("401 Unauthorized", auth, false),
+            // 流式请求只能得到状态码
+            ("401 Unauthorized", auth, true),
+            ("403 Forbidden", "forbidden", true),
+        ] {
+            let response = http_response(status, "application/json", body);
+            let (error, _) = chat_error(response, stream).await?;
+            assert_eq!(classify(&error), ErrorClass::Fatal, "{}", status);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_openai_backend_chat() -> anyhow::Result<()> {
+        let body = r#"{"id":"1","object":"chat.completion","created":0,"model":"mock-model","choices":[{"index":0,"message":{"role":"assistant","content":"你好"},"finish_reason":"stop"}]}"#;
+        let response = http_response("200 OK", "application/json", body);
+        let (base_url, _) = serve(response).await?;
+        let backend = OpenAIBackend::new("my_api_key", base_url);
+
+        let response = backend.chat(request(false)?).await?;
+        assert_eq!(response.choices[0].message.content.as_deref(), Some("你好"));
+
+        let chunk = |content: &str| {
+            format!(
+                r#"data: {{"id":"1","object":"chat.completion.chunk","created":0,"model":"mock-model","choices":[{{"index":0,"delta":{{"content":"{}"}},"finish_reason":null}}]}}"#,
+                content
+            )
+        };
+        let body = format!("{}\n\n{}\n\ndata: [DONE]\n\n", chunk("你"), chunk("好"));
+        let response = http_response("200 OK", "text/event-stream", &body);
+        let (base_url, _) = serve(response).await?;
+        let backend = OpenAIBackend::new("my_api_key", base_url);
+
+        let chunks = backend
+            .chat_stream(request(true)?)
+            .await?
+            .try_collect::<Vec<_>>()
+            .await?;
+        let content = chunks
+            .iter()
+            .filter_map(|chunk| chunk.choices[0].delta.content.as_deref())
+            .collect::<String>();
+        assert_eq!(content, "你好");
+
+        Ok(())
+    }
+}
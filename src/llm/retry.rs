@@ -0,0 +1,335 @@
+use async_openai::error::OpenAIError;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::hash_map::RandomState,
+    fmt::{self, Display},
+    hash::{BuildHasher, Hasher},
+    time::Duration,
+};
+
+/// 后端可以返回的结构化错误，用于告诉重试策略错误是否可以重试
+///
+/// 自定义后端在无法提供 HTTP 状态码时，可以直接返回这个错误
+#[derive(Debug, Clone, PartialEq)]
+pub enum BackendError {
+    /// 触发限流，retry_after 为服务端要求的等待时间
+    RateLimited {
+        message: String,
+        retry_after: Option<Duration>,
+    },
+    /// 临时错误，例如网络抖动、服务端过载
+    Transient(String),
+    /// 不可重试的错误，例如 API Key 错误、余额不足、请求参数错误
+    Fatal(String),
+}
+
+impl Display for BackendError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BackendError::RateLimited { message, .. } => write!(f, "rate limited: {}", message),
+            BackendError::Transient(message) => write!(f, "transient error: {}", message),
+            BackendError::Fatal(message) => write!(f, "fatal error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for BackendError {}
+
+/// 错误分类结果
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorClass {
+    Transient { retry_after: Option<Duration> },
+    Fatal,
+}
+
+/// 判断大模型请求错误是否可以重试
+///
+/// 无法识别的错误按临时错误处理，与之前遇到错误就重试的行为保持一致
+pub fn classify(error: &anyhow::Error) -> ErrorClass {
+    if let Some(error) = error.downcast_ref::<BackendError>() {
+        return match error {
+            BackendError::RateLimited { retry_after, .. } => ErrorClass::Transient {
+                retry_after: *retry_after,
+            },
+            BackendError::Transient(_) => ErrorClass::Transient { retry_after: None },
+            BackendError::Fatal(_) => ErrorClass::Fatal,
+        };
+    }
+
+    if let Some(error) = error.downcast_ref::<OpenAIError>() {
+        return classify_openai_error(error);
+    }
+
+    if let Some(error) = error.downcast_ref::<reqwest::Error>() {
+        return classify_reqwest_error(error);
+    }
+
+    ErrorClass::Transient { retry_after: None }
+}
+
+fn classify_openai_error(error: &OpenAIError) -> ErrorClass {
+    match error {
+        OpenAIError::Reqwest(error) => classify_reqwest_error(error),
+        OpenAIError::ApiError(error) => {
+            // 各厂商的错误类型命名不一，例如 invalid_api_key、invalid_authentication_error、
+            // insufficient_quota、exceeded_current_quota_error，按关键字识别不可重试的错误
+            let kind = format!(
+                "{} {}",
+                error.r#type.as_deref().unwrap_or_default(),
+                error.code.as_deref().unwrap_or_default()
+            )
+            .to_lowercase();
+
+            let fatal = [
+                "auth",
+                "api_key",
+                "quota",
+                "invalid",
+                "permission",
+                "not_found",
+            ]
+            .iter()
+            .any(|keyword| kind.contains(keyword));
+
+            if fatal {
+                ErrorClass::Fatal
+            } else {
+                ErrorClass::Transient { retry_after: None }
+            }
+        }
+        OpenAIError::StreamError(message) => classify_stream_error(message),
+        OpenAIError::JSONDeserialize(_) => ErrorClass::Transient { retry_after: None },
+        OpenAIError::FileSaveError(_)
+        | OpenAIError::FileReadError(_)
+        | OpenAIError::InvalidArgument(_) => ErrorClass::Fatal,
+    }
+}
+
+fn classify_reqwest_error(error: &reqwest::Error) -> ErrorClass {
+    classify_status(error.status().map(|status| status.as_u16()))
+}
+
+// 流式请求失败时只有 "Invalid status code: 401 Unauthorized" 这样的错误信息
+fn classify_stream_error(message: &str) -> ErrorClass {
+    let status = message
+        .strip_prefix("Invalid status code: ")
+        .and_then(|status| status.split_whitespace().next())
+        .and_then(|status| status.parse::<u16>().ok());
+
+    classify_status(status)
+}
+
+fn classify_status(status: Option<u16>) -> ErrorClass {
+    match status {
+        Some(408 | 429) => ErrorClass::Transient { retry_after: None },
+        Some(400..=499) => ErrorClass::Fatal,
+        _ => ErrorClass::Transient { retry_after: None },
+    }
+}
+
+/// 大模型请求的重试策略
+///
+/// 重试发生在同一轮推理内部，不消耗 max_steps
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    // 最多请求次数，包含第一次请求
+    pub max_attempts: usize,
+    // 第一次重试前的等待时间
+    pub initial_backoff: Duration,
+    // 等待时间上限
+    pub max_backoff: Duration,
+    // 每次重试等待时间的增长倍数
+    pub multiplier: f64,
+    // 是否在等待时间上增加随机抖动，避免多个 Agent 同时重试
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// 不重试，第一次失败即返回错误
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// 第 attempt 次请求失败后的指数退避时间，attempt 从 1 开始
+    pub fn backoff(&self, attempt: usize) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(32) as i32;
+        let backoff = self.initial_backoff.as_secs_f64() * self.multiplier.powi(exponent);
+        // 配置错误（例如倍数为负数）时不等待，避免 Duration 转换时 panic
+        let backoff = backoff.min(self.max_backoff.as_secs_f64()).max(0.0);
+
+        let backoff = if self.jitter {
+            // 在 [backoff / 2, backoff] 区间内随机
+            backoff * (0.5 + random_unit() * 0.5)
+        } else {
+            backoff
+        };
+
+        Duration::from_secs_f64(backoff)
+    }
+
+    /// 第 attempt 次请求失败后需要等待的时间，返回 None 表示不再重试
+    pub fn delay(&self, attempt: usize, class: ErrorClass) -> Option<Duration> {
+        match class {
+            ErrorClass::Fatal => None,
+            _ if attempt >= self.max_attempts => None,
+            // 服务端明确要求的等待时间优先于退避时间
+            ErrorClass::Transient {
+                retry_after: Some(retry_after),
+            } => Some(retry_after.max(self.backoff(attempt))),
+            ErrorClass::Transient { retry_after: None } => Some(self.backoff(attempt)),
+        }
+    }
+}
+
+/// [0, 1) 区间内的随机数，RandomState 每次使用随机种子，足够用于退避抖动
+fn random_unit() -> f64 {
+    let value = RandomState::new().build_hasher().finish();
+    (value >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use async_openai::error::ApiError;
+
+    fn api_error(r#type: &str, code: Option<&str>) -> anyhow::Error {
+        OpenAIError::ApiError(ApiError {
+            message: "error".to_string(),
+            r#type: Some(r#type.to_string()),
+            param: None,
+            code: code.map(ToString::to_string),
+        })
+        .into()
+    }
+
+    #[test]
+    fn test_classify_errors() {
+        let transient = ErrorClass::Transient { retry_after: None };
+
+        assert_eq!(classify(&anyhow!("connection reset")), transient);
+        assert_eq!(
+            classify(&api_error("invalid_request_error", Some("invalid_api_key"))),
+            ErrorClass::Fatal
+        );
+        assert_eq!(
+            classify(&api_error("exceeded_current_quota_error", None)),
+            ErrorClass::Fatal
+        );
+        assert_eq!(
+            classify(&api_error("engine_overloaded_error", None)),
+            transient
+        );
+        assert_eq!(
+            classify(&OpenAIError::StreamError("eof".to_string()).into()),
+            transient
+        );
+        assert_eq!(
+            classify(
+                &OpenAIError::StreamError("Invalid status code: 401 Unauthorized".to_string())
+                    .into()
+            ),
+            ErrorClass::Fatal
+        );
+        assert_eq!(
+            classify(
+                &OpenAIError::StreamError("Invalid status code: 429 Too Many Requests".to_string())
+                    .into()
+            ),
+            transient
+        );
+        assert_eq!(
+            classify(
+                &BackendError::RateLimited {
+                    message: "slow down".to_string(),
+                    retry_after: Some(Duration::from_secs(5)),
+                }
+                .into()
+            ),
+            ErrorClass::Transient {
+                retry_after: Some(Duration::from_secs(5))
+            }
+        );
+        assert_eq!(
+            classify(
+                &anyhow::Error::from(BackendError::Fatal("bad key".to_string()))
+                    .context("请求失败")
+            ),
+            ErrorClass::Fatal
+        );
+    }
+
+    #[test]
+    fn test_retry_policy_backoff() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(5),
+            multiplier: 2.0,
+            jitter: false,
+        };
+
+        assert_eq!(policy.backoff(1), Duration::from_secs(1));
+        assert_eq!(policy.backoff(2), Duration::from_secs(2));
+        assert_eq!(policy.backoff(3), Duration::from_secs(4));
+        assert_eq!(policy.backoff(4), Duration::from_secs(5));
+        assert_eq!(policy.backoff(100), Duration::from_secs(5));
+
+        let policy = RetryPolicy {
+            jitter: true,
+            ..policy
+        };
+        for _ in 0..100 {
+            let backoff = policy.backoff(3);
+            assert!(backoff >= Duration::from_secs(2) && backoff <= Duration::from_secs(4));
+        }
+
+        // 负数倍数不会导致 panic
+        let policy = RetryPolicy {
+            multiplier: -2.0,
+            jitter: false,
+            ..policy
+        };
+        assert_eq!(policy.backoff(2), Duration::ZERO);
+        assert_eq!(policy.backoff(3), Duration::from_secs(4));
+    }
+
+    #[test]
+    fn test_retry_policy_delay() {
+        let policy = RetryPolicy {
+            jitter: false,
+            ..Default::default()
+        };
+        let transient = ErrorClass::Transient { retry_after: None };
+
+        assert_eq!(policy.delay(1, transient), Some(Duration::from_secs(1)));
+        assert_eq!(policy.delay(2, transient), Some(Duration::from_secs(2)));
+        assert_eq!(policy.delay(3, transient), None);
+        assert_eq!(policy.delay(1, ErrorClass::Fatal), None);
+        assert_eq!(
+            policy.delay(
+                1,
+                ErrorClass::Transient {
+                    retry_after: Some(Duration::from_secs(10))
+                }
+            ),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(RetryPolicy::none().delay(1, transient), None);
+    }
+}
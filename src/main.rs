@@ -1,8 +1,19 @@
-use async_openai::types::ChatCompletionRequestMessage;
 use chrono::prelude::*;
 use futures::StreamExt;
-use my_agent::agent::{ReActAgent, ReActAgentConfig};
-use std::env;
+use my_agent::{
+    agent::{AgentEvent, CancellationToken, Checkpoint, ReActAgent, ReActAgentConfig, ToolMode},
+    cassette::{Cassette, CassetteMode},
+    history::{ReportFormat, RunHistory},
+    llm::{CassetteBackend, ChatBackend, OpenAIBackend},
+    memory::{Embedder, HashEmbedder, LongMemory, OpenAIEmbedder},
+    tools::{Search, ToolRegistry},
+};
+use std::{
+    env,
+    io::{self, Write},
+    path::PathBuf,
+    sync::Arc,
+};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -12,41 +23,240 @@ async fn main() -> anyhow::Result<()> {
     let model = env::var("OPENAI_MODEL").expect("Missing OPENAI_MODEL");
     let api_base = env::var("OPENAI_API_BASE").expect("Missing OPENAI_API_BASE");
 
+    // 设置 AGENT_CHECKPOINT 后每轮保存检查点，检查点文件存在时从中恢复运行
+    let checkpoint_path = env::var("AGENT_CHECKPOINT").ok().map(PathBuf::from);
+    let checkpoint = match &checkpoint_path {
+        Some(path) if path.exists() => Some(Checkpoint::load(path)?),
+        _ => None,
+    };
+
+    // 模型不支持工具调用时设置 AGENT_TOOL_MODE=prompted，在提示词中描述工具
+    let tool_mode = match env::var("AGENT_TOOL_MODE") {
+        Ok(mode) => mode.parse::<ToolMode>()?,
+        Err(_) => ToolMode::default(),
+    };
+
     let config = ReActAgentConfig::builder()
-        .set_api_key(api_key)
+        .set_api_key(api_key.as_str())
         .set_model(model)
         .try_set_base_url(api_base.as_str())?
         .set_max_steps(10_usize)
+        .set_checkpoint_path(checkpoint_path)
+        .set_tool_mode(tool_mode)
         .build()?;
 
-    let agent = ReActAgent::new(config);
+    // 设置 AGENT_LONG_MEMORY 后在多次运行之间保留长期记忆，
+    // 设置 OPENAI_EMBEDDING_MODEL 时使用该模型计算向量，否则使用本地的特征哈希
+    let long_memory = match env::var("AGENT_LONG_MEMORY") {
+        Ok(path) => {
+            let embedder: Arc<dyn Embedder> = match env::var("OPENAI_EMBEDDING_MODEL") {
+                Ok(model) => Arc::new(OpenAIEmbedder::new(&api_key, &api_base, &model)),
+                Err(_) => Arc::new(HashEmbedder::default()),
+            };
+            Some(Arc::new(LongMemory::open(path, embedder)?))
+        }
+        Err(_) => None,
+    };
+
+    // 设置 AGENT_HISTORY 后将每次运行的完整记录写入该 SQLite 数据库
+    let history = match env::var("AGENT_HISTORY") {
+        Ok(path) => Some(Arc::new(RunHistory::open(path)?)),
+        Err(_) => None,
+    };
 
     // let question = "周杰伦今年多大了？他的年龄的0.23次方是多少？";
     // let question = "制作一份关于周杰伦的简历";
     let question = "请联网搜索 Context Caching，并告诉我它是什么。";
 
-    let mut stream = agent.invoke(question).await?;
+    // 设置 AGENT_CASSETTE 后录制或回放本次运行的全部大模型和搜索请求
+    match env::var("AGENT_CASSETTE") {
+        Ok(path) => {
+            let mode = env::var("AGENT_CASSETTE_MODE")
+                .unwrap_or_else(|_| "record".to_string())
+                .parse::<CassetteMode>()?;
+            let cassette = Arc::new(Cassette::open(path, mode)?);
+            let tools = ToolRegistry::builtin().with(Search::new().with_cassette(cassette.clone()));
+
+            let backend = CassetteBackend::new(OpenAIBackend::new(api_key, api_base), cassette);
+            run(
+                ReActAgent::with_backend(config, backend).with_tools(tools),
+                question,
+                checkpoint,
+                long_memory,
+                history,
+            )
+            .await
+        }
+        Err(_) => {
+            run(
+                ReActAgent::new(config),
+                question,
+                checkpoint,
+                long_memory,
+                history,
+            )
+            .await
+        }
+    }
+}
+
+async fn run<B: ChatBackend + 'static>(
+    mut agent: ReActAgent<B>,
+    question: &str,
+    checkpoint: Option<Checkpoint>,
+    long_memory: Option<Arc<LongMemory>>,
+    history: Option<Arc<RunHistory>>,
+) -> anyhow::Result<()> {
+    if let Some(long_memory) = long_memory {
+        agent = agent.with_long_memory(long_memory);
+    }
+    if let Some(history) = &history {
+        agent = agent.with_history(history.clone());
+    }
+
+    // Ctrl-C 时取消运行，等待 Agent 输出取消事件后正常退出
+    let cancel = CancellationToken::new();
+    tokio::spawn({
+        let cancel = cancel.clone();
+        async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                cancel.cancel();
+            }
+        }
+    });
+
+    let mut stream = match checkpoint {
+        Some(checkpoint) => agent.resume_with_cancellation(checkpoint, cancel).await?,
+        None => agent.invoke_with_cancellation(question, cancel).await?,
+    };
+
+    let mut streaming = false;
 
-    while let Some(response) = stream.next().await {
-        let response = response?;
+    while let Some(event) = stream.next().await {
+        // 错误事件之后仍会输出结束事件并保存运行记录，打印错误后继续
+        let event = match event {
+            Ok(event) => event,
+            Err(e) => {
+                if std::mem::take(&mut streaming) {
+                    println!();
+                }
+                println!("[{}] Error: {:#}", now(), e);
+                continue;
+            }
+        };
 
-        if let Some((role, content)) = match response {
-            ChatCompletionRequestMessage::User(message) => {
-                Some(("User", serde_json::to_string(&message.content)?))
+        if let Some((role, content)) = match event {
+            AgentEvent::UserMessage { content } => Some(("User", content)),
+            AgentEvent::Resumed { step } => Some(("Agent", format!("从第 {} 轮恢复运行", step))),
+            AgentEvent::StepStarted { step, max_steps } => {
+                Some(("Step", format!("{}/{}", step, max_steps)))
             }
-            ChatCompletionRequestMessage::Assistant(message) => {
-                message.content.map(|content| ("Assistant", content))
+            AgentEvent::ContentDelta { delta, .. } => {
+                // 流式输出的片段直接打印在同一行
+                if !streaming {
+                    streaming = true;
+                    print!("[{}] Assistant: ", now());
+                }
+                print!("{}", delta);
+                io::stdout().flush()?;
+                None
             }
-            ChatCompletionRequestMessage::Tool(message) => Some((
-                "Tool",
-                format!("{} - {}", message.tool_call_id, message.content),
+            AgentEvent::MemoryCompacted { evicted, summary } => Some((
+                "Memory",
+                match summary {
+                    Some(summary) => format!("已总结 {} 条较早的消息: {}", evicted, summary),
+                    None => format!("总结失败，已丢弃 {} 条较早的消息", evicted),
+                },
             )),
-            _ => None,
+            AgentEvent::ModelResponded { content, .. } => {
+                if std::mem::take(&mut streaming) {
+                    println!();
+                    None
+                } else {
+                    content
+                        .filter(|content| !content.is_empty())
+                        .map(|content| ("Assistant", content))
+                }
+            }
+            AgentEvent::ToolCallStarted {
+                id,
+                name,
+                arguments,
+            } => Some(("Tool", format!("{} - {}({})", id, name, arguments))),
+            AgentEvent::ToolCallFinished { id, result, .. } => {
+                Some(("Tool", format!("{} - {}", id, result)))
+            }
+            AgentEvent::ToolFailed { id, error, .. } => {
+                Some(("Tool", format!("{} - 调用失败: {}", id, error)))
+            }
+            AgentEvent::InvalidResponse { error, .. } => {
+                Some(("Agent", format!("回复无法解析为命令: {}", error)))
+            }
+            AgentEvent::Retrying {
+                attempt,
+                delay_ms,
+                error,
+                ..
+            } => Some((
+                "Retry",
+                format!(
+                    "第 {} 次请求大模型失败，{} 毫秒后重试... {}",
+                    attempt, delay_ms, error
+                ),
+            )),
+            AgentEvent::FinalAnswer { answer } => Some(("Answer", answer)),
+            AgentEvent::BudgetExceeded { reason, usage } => Some((
+                "Agent",
+                format!(
+                    "{}，输入 {} tokens，输出 {} tokens，费用 {:.4}",
+                    reason, usage.prompt_tokens, usage.completion_tokens, usage.cost
+                ),
+            )),
+            AgentEvent::MaxStepsReached { max_steps } => Some((
+                "Agent",
+                format!("已达到最大调用轮数 {}，任务未完成", max_steps),
+            )),
+            AgentEvent::RunFinished { status } => {
+                Some(("Agent", format!("运行结束: {:?}", status)))
+            }
+            AgentEvent::Cancelled { step, transcript } => {
+                // 取消时可能正处于流式输出中
+                if std::mem::take(&mut streaming) {
+                    println!();
+                }
+                Some((
+                    "Agent",
+                    format!(
+                        "运行已在第 {} 轮取消，共 {} 条对话记录",
+                        step,
+                        transcript.len()
+                    ),
+                ))
+            }
         } {
-            let local = Local::now().format("%m-%d %H:%M:%S").to_string();
-            println!("[{}] {}: {}", local, role, content);
+            println!("[{}] {}: {}", now(), role, content);
+        }
+    }
+
+    // 设置 AGENT_REPORT 后将本次运行导出为报告，扩展名为 .html 时导出 HTML，否则导出 Markdown
+    if let (Some(history), Ok(path)) = (history, env::var("AGENT_REPORT")) {
+        let path = PathBuf::from(path);
+        let format = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("html") => ReportFormat::Html,
+            _ => ReportFormat::Markdown,
+        };
+
+        if let Some(run) = history.list(1)?.first() {
+            if let Some(record) = history.fetch(run.id)? {
+                std::fs::write(&path, record.export(format)?)?;
+                println!("[{}] Agent: 报告已导出到 {}", now(), path.display());
+            }
         }
     }
 
     Ok(())
 }
+
+fn now() -> String {
+    Local::now().format("%m-%d %H:%M:%S").to_string()
+}
@@ -0,0 +1,155 @@
+use crate::utils::fnv1a;
+use anyhow::Result;
+use async_openai::{
+    config::OpenAIConfig,
+    types::{CreateEmbeddingRequestArgs, EmbeddingInput},
+    Client,
+};
+use futures::future::BoxFuture;
+
+/// 将文本转换为向量，用于长期记忆的语义检索
+pub trait Embedder: Send + Sync {
+    fn embed<'a>(&'a self, texts: &'a [String]) -> BoxFuture<'a, Result<Vec<Vec<f32>>>>;
+}
+
+/// 使用 OpenAI 兼容接口的 embeddings 模型
+pub struct OpenAIEmbedder {
+    client: Client<OpenAIConfig>,
+    model: String,
+}
+
+impl OpenAIEmbedder {
+    pub fn new(api_key: &str, base_url: &str, model: &str) -> Self {
+        let config = OpenAIConfig::new()
+            .with_api_key(api_key)
+            .with_api_base(base_url);
+
+        Self {
+            client: Client::with_config(config),
+            model: model.to_string(),
+        }
+    }
+}
+
+impl Embedder for OpenAIEmbedder {
+    fn embed<'a>(&'a self, texts: &'a [String]) -> BoxFuture<'a, Result<Vec<Vec<f32>>>> {
+        Box::pin(async move {
+            let request = CreateEmbeddingRequestArgs::default()
+                .model(self.model.as_str())
+                .input(EmbeddingInput::StringArray(texts.to_vec()))
+                .build()?;
+
+            let mut data = self.client.embeddings().create(request).await?.data;
+            data.sort_by_key(|embedding| embedding.index);
+
+            Ok(data
+                .into_iter()
+                .map(|embedding| embedding.embedding)
+                .collect())
+        })
+    }
+}
+
+/// 不依赖模型的特征哈希：英文按单词、中文按相邻两个字统计词频，适合离线使用和测试
+#[derive(Debug, Clone, Copy)]
+pub struct HashEmbedder {
+    dimensions: usize,
+}
+
+impl HashEmbedder {
+    pub fn new(dimensions: usize) -> Self {
+        Self {
+            dimensions: dimensions.max(1),
+        }
+    }
+
+    fn embed_text(&self, text: &str) -> Vec<f32> {
+        let mut vector = vec![0.0; self.dimensions];
+
+        for feature in features(text) {
+            vector[(fnv1a(&feature) % self.dimensions as u64) as usize] += 1.0;
+        }
+
+        let norm = vector.iter().map(|value| value * value).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            vector.iter_mut().for_each(|value| *value /= norm);
+        }
+
+        vector
+    }
+}
+
+impl Default for HashEmbedder {
+    fn default() -> Self {
+        Self::new(256)
+    }
+}
+
+impl Embedder for HashEmbedder {
+    fn embed<'a>(&'a self, texts: &'a [String]) -> BoxFuture<'a, Result<Vec<Vec<f32>>>> {
+        Box::pin(async move { Ok(texts.iter().map(|text| self.embed_text(text)).collect()) })
+    }
+}
+
+fn features(text: &str) -> Vec<String> {
+    let mut features = Vec::new();
+    let mut word = String::new();
+    let mut previous: Option<char> = None;
+
+    for c in text.chars().flat_map(char::to_lowercase) {
+        if c.is_ascii_alphanumeric() {
+            word.push(c);
+            previous = None;
+            continue;
+        }
+
+        if !word.is_empty() {
+            features.push(std::mem::take(&mut word));
+        }
+
+        if c.is_alphanumeric() {
+            // 非 ASCII 文字没有空格分词，使用单字和相邻两个字
+            features.push(c.to_string());
+            if let Some(previous) = previous {
+                features.push(format!("{}{}", previous, c));
+            }
+            previous = Some(c);
+        } else {
+            previous = None;
+        }
+    }
+
+    if !word.is_empty() {
+        features.push(word);
+    }
+
+    features
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_hash_embedder() -> Result<()> {
+        let embedder = HashEmbedder::default();
+        let texts = vec![
+            "周杰伦的年龄".to_string(),
+            "周杰伦今年多大".to_string(),
+            "Context Caching".to_string(),
+        ];
+
+        let vectors = embedder.embed(&texts).await?;
+        assert_eq!(vectors.len(), 3);
+        assert!(vectors.iter().all(|vector| vector.len() == 256));
+
+        let dot = |a: &[f32], b: &[f32]| a.iter().zip(b).map(|(a, b)| a * b).sum::<f32>();
+        assert!((dot(&vectors[0], &vectors[0]) - 1.0).abs() < 1e-5);
+        assert!(dot(&vectors[0], &vectors[1]) > dot(&vectors[0], &vectors[2]));
+
+        // 相同文本的向量相同
+        assert_eq!(embedder.embed(&texts[..1]).await?[0], vectors[0]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,208 @@
+use super::Embedder;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// 长期记忆的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryKind {
+    // 主动记录的笔记
+    Note,
+    // 已完成任务的问题和答案
+    TaskOutcome,
+}
+
+/// 一条长期记忆及其向量
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MemoryEntry {
+    pub id: u64,
+    pub kind: MemoryKind,
+    pub content: String,
+    pub embedding: Vec<f32>,
+    // 创建时间，Unix 时间戳（秒）
+    pub created_at: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct MemoryFile {
+    entries: Vec<MemoryEntry>,
+}
+
+/// 保存在本地文件中的长期记忆，按向量的余弦相似度检索，在多次运行之间保留
+pub struct LongMemory {
+    path: PathBuf,
+    embedder: Arc<dyn Embedder>,
+    entries: Mutex<Vec<MemoryEntry>>,
+}
+
+impl LongMemory {
+    /// 打开记忆文件，文件不存在时从空记忆开始，第一次写入时创建
+    pub fn open(path: impl AsRef<Path>, embedder: Arc<dyn Embedder>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+
+        let entries = if path.exists() {
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("读取长期记忆失败: {}", path.display()))?;
+            serde_json::from_str::<MemoryFile>(&content)?.entries
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self {
+            path,
+            embedder,
+            entries: Mutex::new(entries),
+        })
+    }
+
+    /// 计算向量后写入一条记忆，并立即保存到文件
+    pub async fn add(&self, kind: MemoryKind, content: &str) -> Result<MemoryEntry> {
+        let embedding = self.embed(content).await?;
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+
+        let mut entries = self.entries.lock().unwrap();
+        let entry = MemoryEntry {
+            id: entries.iter().map(|entry| entry.id + 1).max().unwrap_or(1),
+            kind,
+            content: content.to_string(),
+            embedding,
+            created_at,
+        };
+        entries.push(entry.clone());
+
+        if let Err(e) = self.save(&entries) {
+            entries.pop();
+            return Err(e);
+        }
+
+        Ok(entry)
+    }
+
+    /// 返回与 query 最相关的 k 条记忆及其相似度，按相似度从高到低排列
+    pub async fn search(&self, query: &str, k: usize) -> Result<Vec<(MemoryEntry, f32)>> {
+        if k == 0 || self.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let query = self.embed(query).await?;
+
+        let entries = self.entries.lock().unwrap();
+        let mut scored = entries
+            .iter()
+            // 更换 embedding 模型后维度可能不一致，无法比较
+            .filter(|entry| entry.embedding.len() == query.len())
+            .map(|entry| (entry.clone(), cosine_similarity(&entry.embedding, &query)))
+            .collect::<Vec<_>>();
+
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        scored.truncate(k);
+
+        Ok(scored)
+    }
+
+    pub fn entries(&self) -> Vec<MemoryEntry> {
+        self.entries.lock().unwrap().clone()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        self.embedder
+            .embed(&[text.to_string()])
+            .await?
+            .pop()
+            .ok_or_else(|| anyhow!("embedding 结果为空"))
+    }
+
+    /// 先写入临时文件再重命名，避免写入过程中进程退出导致记忆文件损坏
+    fn save(&self, entries: &[MemoryEntry]) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let file = MemoryFile {
+            entries: entries.to_vec(),
+        };
+
+        let temp = self.path.with_extension("tmp");
+        fs::write(&temp, serde_json::to_string(&file)?)?;
+        fs::rename(&temp, &self.path)
+            .with_context(|| format!("保存长期记忆失败: {}", self.path.display()))?;
+
+        Ok(())
+    }
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot = a.iter().zip(b).map(|(a, b)| a * b).sum::<f32>();
+    let norm =
+        a.iter().map(|a| a * a).sum::<f32>().sqrt() * b.iter().map(|b| b * b).sum::<f32>().sqrt();
+
+    if norm == 0.0 {
+        0.0
+    } else {
+        dot / norm
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::HashEmbedder;
+
+    #[tokio::test]
+    async fn test_long_memory_search_and_persist() -> Result<()> {
+        let path = std::env::temp_dir().join(format!(
+            "my-agent-long-memory-{}/memory.json",
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+
+        let memory = LongMemory::open(&path, Arc::new(HashEmbedder::default()))?;
+        assert!(memory.search("周杰伦", 3).await?.is_empty());
+
+        memory
+            .add(MemoryKind::TaskOutcome, "问题: 周杰伦的年龄\n答案: 45 岁")
+            .await?;
+        memory
+            .add(MemoryKind::Note, "Context Caching 可以降低长文本的费用")
+            .await?;
+        memory.add(MemoryKind::Note, "北京今天晴").await?;
+
+        let results = memory.search("周杰伦今年多大", 2).await?;
+        assert_eq!(results.len(), 2);
+        assert!(results[0].0.content.contains("周杰伦"));
+        assert!(results[0].1 >= results[1].1);
+
+        // 重新打开后记忆仍然存在
+        let reopened = LongMemory::open(&path, Arc::new(HashEmbedder::default()))?;
+        assert_eq!(reopened.entries(), memory.entries());
+        assert_eq!(
+            reopened.entries().iter().map(|e| e.id).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+
+        // 向量维度不同的记忆不参与检索
+        let other = LongMemory::open(&path, Arc::new(HashEmbedder::new(16)))?;
+        assert!(other.search("周杰伦", 3).await?.is_empty());
+
+        fs::remove_dir_all(path.parent().unwrap())?;
+
+        Ok(())
+    }
+}
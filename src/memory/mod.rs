@@ -1,3 +1,11 @@
+mod embedder;
+mod long_memory;
 mod short_memory;
+mod strategy;
+mod tokenizer;
 
+pub use embedder::{Embedder, HashEmbedder, OpenAIEmbedder};
+pub use long_memory::{LongMemory, MemoryEntry, MemoryKind};
 pub(crate) use short_memory::ShortMemory;
+pub use strategy::MemoryStrategy;
+pub use tokenizer::{ApproxTokenizer, Tokenizer};
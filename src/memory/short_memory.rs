@@ -1,9 +1,19 @@
+use super::{ApproxTokenizer, Tokenizer};
 use async_openai::types::{ChatCompletionRequestMessage, ChatCompletionRequestSystemMessage};
+use std::sync::Arc;
 
 #[derive(Clone)]
 pub(crate) struct ShortMemory {
     system: Option<ChatCompletionRequestSystemMessage>,
     history: Vec<ChatCompletionRequestMessage>,
+    // 系统消息和每条历史消息的 token 数
+    system_tokens: usize,
+    history_tokens: Vec<usize>,
+    tokenizer: Arc<dyn Tokenizer>,
+    // 上下文 token 上限，超出时丢弃最早的对话，None 表示不限制
+    max_tokens: Option<usize>,
+    // 自上次 take_transcript 以来追加的全部消息，不受上下文上限影响
+    transcript: Vec<ChatCompletionRequestMessage>,
 }
 
 impl ShortMemory {
@@ -11,18 +21,48 @@ impl ShortMemory {
         Self {
             system: None,
             history: Vec::new(),
+            system_tokens: 0,
+            history_tokens: Vec::new(),
+            tokenizer: Arc::new(ApproxTokenizer),
+            max_tokens: None,
+            transcript: Vec::new(),
+        }
+    }
+
+    pub fn with_budget(tokenizer: Arc<dyn Tokenizer>, max_tokens: Option<usize>) -> Self {
+        Self {
+            tokenizer,
+            max_tokens,
+            ..Self::new()
         }
     }
 
     pub fn append(&mut self, message: ChatCompletionRequestMessage) {
+        let tokens = self.count(&message);
+        self.transcript.push(message.clone());
+
         match message {
             ChatCompletionRequestMessage::System(system) => {
                 self.system = Some(system);
+                self.system_tokens = tokens;
             }
             _ => {
                 self.history.push(message);
+                self.history_tokens.push(tokens);
             }
         }
+
+        if let Some(max_tokens) = self.max_tokens {
+            self.evict(max_tokens);
+        }
+    }
+
+    /// 在历史消息的最前面插入消息，例如被移除对话的总结
+    pub fn prepend(&mut self, message: ChatCompletionRequestMessage) {
+        let tokens = self.count(&message);
+
+        self.history.insert(0, message);
+        self.history_tokens.insert(0, tokens);
     }
 
     pub fn messages(&self) -> Vec<ChatCompletionRequestMessage> {
@@ -33,12 +73,61 @@ impl ShortMemory {
         messages.extend(self.history.iter().cloned());
         messages
     }
+
+    /// 取出自上次调用以来追加的全部消息，包括已被移除的消息
+    pub fn take_transcript(&mut self) -> Vec<ChatCompletionRequestMessage> {
+        std::mem::take(&mut self.transcript)
+    }
+
+    /// 系统消息和历史消息的 token 总数
+    pub fn tokens(&self) -> usize {
+        self.system_tokens + self.history_tokens.iter().sum::<usize>()
+    }
+
+    /// 从最早的对话开始移除，直到 token 总数不超过 max_tokens，返回被移除的消息
+    ///
+    /// 带 tool_calls 的助手消息和它的工具消息作为整体移除，最近的一组对话始终保留
+    pub fn evict(&mut self, max_tokens: usize) -> Vec<ChatCompletionRequestMessage> {
+        let mut evicted = Vec::new();
+
+        while self.tokens() > max_tokens {
+            let Some(end) = self.group_starts().get(1).copied() else {
+                break;
+            };
+
+            self.history_tokens.drain(..end);
+            evicted.extend(self.history.drain(..end));
+        }
+
+        evicted
+    }
+
+    /// 历史消息中每组对话的起始位置，工具消息与之前的助手消息属于同一组
+    fn group_starts(&self) -> Vec<usize> {
+        self.history
+            .iter()
+            .enumerate()
+            .filter(|(index, message)| {
+                *index == 0 || !matches!(message, ChatCompletionRequestMessage::Tool(_))
+            })
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    fn count(&self, message: &ChatCompletionRequestMessage) -> usize {
+        // 按序列化后的内容计算，包含工具调用参数和消息结构的开销
+        let text = serde_json::to_string(message).unwrap_or_default();
+        self.tokenizer.count(&text)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use async_openai::types::{
-        ChatCompletionRequestAssistantMessage, ChatCompletionRequestUserMessage,
+        ChatCompletionMessageToolCall, ChatCompletionRequestAssistantMessage,
+        ChatCompletionRequestAssistantMessageArgs, ChatCompletionRequestToolMessageArgs,
+        ChatCompletionRequestUserMessage, ChatCompletionRequestUserMessageArgs,
+        ChatCompletionToolType, FunctionCall,
     };
 
     use super::*;
@@ -86,4 +175,114 @@ mod tests {
 
         assert_eq!(short_memory.messages(), vec![system, user, assistant]);
     }
+
+    // 每个字符算一个 token，方便计算
+    struct CharTokenizer;
+
+    impl Tokenizer for CharTokenizer {
+        fn count(&self, text: &str) -> usize {
+            text.chars().count()
+        }
+    }
+
+    fn text(role: &str, content: &str) -> ChatCompletionRequestMessage {
+        match role {
+            "user" => ChatCompletionRequestUserMessageArgs::default()
+                .content(content)
+                .build()
+                .unwrap()
+                .into(),
+            _ => ChatCompletionRequestAssistantMessageArgs::default()
+                .content(content)
+                .build()
+                .unwrap()
+                .into(),
+        }
+    }
+
+    fn tool_calls(ids: &[&str]) -> ChatCompletionRequestMessage {
+        let tool_calls = ids
+            .iter()
+            .map(|id| ChatCompletionMessageToolCall {
+                id: id.to_string(),
+                r#type: ChatCompletionToolType::Function,
+                function: FunctionCall {
+                    name: "search".to_string(),
+                    arguments: "{}".to_string(),
+                },
+            })
+            .collect::<Vec<_>>();
+
+        ChatCompletionRequestAssistantMessageArgs::default()
+            .tool_calls(tool_calls)
+            .build()
+            .unwrap()
+            .into()
+    }
+
+    fn tool(id: &str) -> ChatCompletionRequestMessage {
+        ChatCompletionRequestToolMessageArgs::default()
+            .tool_call_id(id)
+            .content("result")
+            .build()
+            .unwrap()
+            .into()
+    }
+
+    #[test]
+    fn test_short_memory_evicts_tool_call_groups_together() {
+        let messages = [
+            tool_calls(&["call_1", "call_2"]),
+            tool("call_1"),
+            tool("call_2"),
+            text("assistant", "思考"),
+            tool_calls(&["call_3"]),
+            tool("call_3"),
+        ];
+
+        let mut unbounded = ShortMemory::with_budget(Arc::new(CharTokenizer), None);
+        unbounded.append(ChatCompletionRequestSystemMessage::default().into());
+        for message in messages.iter().cloned() {
+            unbounded.append(message);
+        }
+
+        // 上限只够保留最后两组对话
+        let max_tokens = unbounded.tokens() - unbounded.history_tokens[..3].iter().sum::<usize>();
+
+        let mut short_memory = ShortMemory::with_budget(Arc::new(CharTokenizer), Some(max_tokens));
+        short_memory.append(ChatCompletionRequestSystemMessage::default().into());
+        for message in messages.iter().cloned() {
+            short_memory.append(message);
+        }
+
+        assert!(short_memory.tokens() <= max_tokens);
+        assert_eq!(short_memory.history, messages[3..]);
+        assert!(matches!(
+            short_memory.messages()[0],
+            ChatCompletionRequestMessage::System(_)
+        ));
+
+        // 移除后剩余的第一条历史消息不会是工具消息
+        let evicted = unbounded.evict(max_tokens - 1);
+        assert_eq!(evicted, messages[..4]);
+        assert_eq!(unbounded.history, messages[4..]);
+    }
+
+    #[test]
+    fn test_short_memory_keeps_latest_group() {
+        let mut short_memory = ShortMemory::with_budget(Arc::new(CharTokenizer), Some(1));
+        short_memory.append(text("user", "问题"));
+        short_memory.append(text("assistant", "很长的回答"));
+
+        // 超出上限时仍保留最近的一组对话
+        assert_eq!(short_memory.history, vec![text("assistant", "很长的回答")]);
+        assert_eq!(short_memory.history_tokens.len(), 1);
+
+        // 被移除的消息仍保留在对话记录中
+        assert_eq!(
+            short_memory.take_transcript(),
+            vec![text("user", "问题"), text("assistant", "很长的回答")]
+        );
+        assert!(short_memory.take_transcript().is_empty());
+    }
 }
@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+
+/// 短期记忆超出上下文上限时的处理方式
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryStrategy {
+    /// 直接丢弃最早的对话
+    #[default]
+    Truncate,
+    /// 由大模型将最早的对话总结为一条消息，保留事实、写入的文件和未完成的子目标
+    Summarize,
+}
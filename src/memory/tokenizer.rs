@@ -0,0 +1,33 @@
+/// 计算文本的 token 数，可替换为与模型一致的分词器
+pub trait Tokenizer: Send + Sync {
+    fn count(&self, text: &str) -> usize;
+}
+
+/// 不依赖词表的估算：中文等非 ASCII 字符每个字符算一个 token，ASCII 字符每 4 个算一个 token
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ApproxTokenizer;
+
+impl Tokenizer for ApproxTokenizer {
+    fn count(&self, text: &str) -> usize {
+        let ascii = text.chars().filter(char::is_ascii).count();
+        let others = text.chars().count() - ascii;
+
+        others + ascii.div_ceil(4)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_approx_tokenizer() {
+        let tokenizer = ApproxTokenizer;
+
+        assert_eq!(tokenizer.count(""), 0);
+        assert_eq!(tokenizer.count("abcd"), 1);
+        assert_eq!(tokenizer.count("abcde"), 2);
+        assert_eq!(tokenizer.count("周杰伦"), 3);
+        assert_eq!(tokenizer.count("周杰伦 Jay"), 4);
+    }
+}
@@ -1,4 +1,5 @@
 #[allow(clippy::module_inception)]
 mod planning;
+mod report;
 
 pub(crate) use planning::Planning;
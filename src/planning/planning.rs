@@ -1,51 +1,96 @@
-use crate::tools::Tools;
+use super::report::Report;
+use crate::{
+    agent::{Response, ToolMode},
+    history::{ReportFormat, RunRecord},
+    llm::{ChatBackend, ChatStream},
+    tools::{parse_lenient, ToolRegistry},
+};
 use anyhow::Result;
-use async_openai::{
-    config::OpenAIConfig,
-    types::{
-        ChatCompletionRequestAssistantMessage, ChatCompletionRequestAssistantMessageArgs,
-        ChatCompletionRequestMessage, ChatCompletionRequestSystemMessage,
-        ChatCompletionRequestSystemMessageArgs, ChatCompletionRequestUserMessage,
-        ChatCompletionRequestUserMessageArgs, ChatCompletionToolChoiceOption,
-        CreateChatCompletionRequest, CreateChatCompletionRequestArgs, CreateChatCompletionResponse,
-    },
-    Client,
+use async_openai::types::{
+    ChatCompletionRequestAssistantMessage, ChatCompletionRequestAssistantMessageArgs,
+    ChatCompletionRequestMessage, ChatCompletionRequestSystemMessage,
+    ChatCompletionRequestSystemMessageArgs, ChatCompletionRequestUserMessage,
+    ChatCompletionRequestUserMessageArgs, ChatCompletionRequestUserMessageContent,
+    ChatCompletionStreamOptions, ChatCompletionToolChoiceOption, CreateChatCompletionRequest,
+    CreateChatCompletionRequestArgs, CreateChatCompletionResponse,
 };
+use serde::Serialize;
 use tera::{Context, Tera};
 
 pub(crate) struct Planning {
     engine: Tera,
+    tool_mode: ToolMode,
+}
+
+/// 提示词模式下写入系统消息的命令说明
+#[derive(Serialize)]
+struct PromptCommand {
+    name: String,
+    description: String,
+    parameters: String,
 }
 
 impl Planning {
     pub fn try_new() -> Result<Self> {
         let engine = Tera::new("templates/**/*")?;
-        Ok(Planning { engine })
+        Ok(Planning {
+            engine,
+            tool_mode: ToolMode::default(),
+        })
+    }
+
+    pub fn with_tool_mode(mut self, tool_mode: ToolMode) -> Self {
+        self.tool_mode = tool_mode;
+        self
     }
 
     /// 将用户问题构建进系统消息
     ///
     /// 系统消息模版内容块说明：
     ///     头部内容：要求Agent独立解决问题，并且要严格遵循法律法规
-    ///     GOAL: 需要解决的目标，即用户提出的问题
+    ///     GOAL: 需要解决的目标，即用户提出的问题，多轮对话时附带此前的问题
     ///     Constraints: 约束条件
     ///     Commands: 工具集，Agent可以使用的工具
     ///     Resources: 资源，Agent可以调用的资源
     ///     (重点)Performance Evaluation: 性能评估，包含反思、自我批评、思维链、子问题分解
     ///     Response Format: 响应格式，这里要求Agent返回json格式，方便反序列化
+    ///
+    /// 提示词模式下工具作为命令写入系统消息，大模型按 Response 的 JSON 格式回复
     pub fn build_system_message(
         &self,
         question: &str,
+        previous_questions: &[String],
+        memories: &[String],
+        tools: &ToolRegistry,
         language: &str,
     ) -> Result<ChatCompletionRequestSystemMessage> {
-        // todo!: 可定义的人设说明
-        let response_format = self
-            .engine
-            .render("response_format.prompt", &Default::default())?;
+        let commands = match self.tool_mode {
+            ToolMode::Native => Vec::new(),
+            ToolMode::Prompted => tools
+                .definitions()
+                .into_iter()
+                .map(|tool| PromptCommand {
+                    name: tool.function.name,
+                    description: tool.function.description.unwrap_or_default(),
+                    parameters: tool
+                        .function
+                        .parameters
+                        .map(|parameters| parameters.to_string())
+                        .unwrap_or_default(),
+                })
+                .collect(),
+        };
 
+        // todo!: 可定义的人设说明
         let mut context = Context::new();
+        context.insert("commands", &commands);
+        let response_format = self.engine.render("response_format.prompt", &context)?;
+
         context.insert("language", language);
         context.insert("question", question);
+        context.insert("previous_questions", previous_questions);
+        context.insert("memories", memories);
+        context.insert("tools", &tools.names());
         context.insert("response_format", &response_format);
 
         let system_prompt = self.engine.render("system.prompt", &context)?;
@@ -57,18 +102,43 @@ impl Planning {
         Ok(system_message)
     }
 
-    pub fn _build_command_result(&self, result: &str) -> Result<ChatCompletionRequestUserMessage> {
+    /// 提示词模式下命令的执行结果，作为用户消息返回给大模型
+    pub fn build_command_result(
+        &self,
+        name: &str,
+        result: &str,
+    ) -> Result<ChatCompletionRequestUserMessage> {
+        let mut context = Context::new();
+        context.insert("name", name);
+        context.insert("result", result);
+
+        let content = self.engine.render("command_result.prompt", &context)?;
+
         let user_message = ChatCompletionRequestUserMessageArgs::default()
-            .content(result)
+            .content(content)
             .build()?;
 
         Ok(user_message)
     }
 
-    pub fn _build_fixjson_message(
+    /// 提示词模式下回复无法解析为命令时，要求大模型按格式重新回复
+    pub fn build_invalid_response_message(
         &self,
-        content: &str,
+        error: &str,
     ) -> Result<ChatCompletionRequestUserMessage> {
+        let mut context = Context::new();
+        context.insert("error", error);
+
+        let content = self.engine.render("invalid_response.prompt", &context)?;
+
+        let user_message = ChatCompletionRequestUserMessageArgs::default()
+            .content(content)
+            .build()?;
+
+        Ok(user_message)
+    }
+
+    fn build_fixjson_message(&self, content: &str) -> Result<ChatCompletionRequestUserMessage> {
         let mut context = Context::new();
         context.insert("response", content);
 
@@ -81,6 +151,65 @@ impl Planning {
         Ok(user_message)
     }
 
+    /// 解析提示词模式下大模型的回复，先修复常见的 JSON 格式问题
+    pub fn parse_response(&self, content: &str) -> Result<Response> {
+        Ok(serde_json::from_value(parse_lenient(content)?)?)
+    }
+
+    /// 回复无法解析时请求大模型将其改写为 JSON，请求中不包含对话记录
+    pub async fn fix_response<B: ChatBackend>(
+        &self,
+        backend: &B,
+        model: &str,
+        content: &str,
+    ) -> Result<CreateChatCompletionResponse> {
+        let user_message = self.build_fixjson_message(content)?;
+
+        let request = CreateChatCompletionRequestArgs::default()
+            .model(model)
+            .temperature(0.0)
+            .messages(vec![user_message.into()])
+            .build()?;
+
+        backend.chat(request).await
+    }
+
+    /// 要求大模型停止调用工具，根据已有信息给出最终答案
+    pub fn build_wrap_up_message(&self, reason: &str) -> Result<ChatCompletionRequestUserMessage> {
+        let mut context = Context::new();
+        context.insert("reason", reason);
+
+        let content = self.engine.render("wrap_up.prompt", &context)?;
+
+        let user_message = ChatCompletionRequestUserMessageArgs::default()
+            .content(content)
+            .build()?;
+
+        Ok(user_message)
+    }
+
+    /// 用对话总结替代被移出短期记忆的消息
+    pub fn build_summary_message(&self, summary: &str) -> Result<ChatCompletionRequestUserMessage> {
+        let mut context = Context::new();
+        context.insert("summary", summary);
+
+        let content = self.engine.render("summary.prompt", &context)?;
+
+        let user_message = ChatCompletionRequestUserMessageArgs::default()
+            .content(content)
+            .build()?;
+
+        Ok(user_message)
+    }
+
+    /// 使用 report.md 或 report.html 模版渲染运行报告
+    pub fn build_report(&self, record: &RunRecord, format: ReportFormat) -> Result<String> {
+        let context = Context::from_serialize(Report::new(record))?;
+        let template = format!("report.{}", format.extension());
+
+        Ok(self.engine.render(&template, &context)?)
+    }
+
     pub fn build_user_message(&self, content: &str) -> Result<ChatCompletionRequestUserMessage> {
         let user_message = ChatCompletionRequestUserMessageArgs::default()
             .content(content)
@@ -100,39 +229,139 @@ impl Planning {
         Ok(assistant_message)
     }
 
-    pub async fn execute(
+    pub async fn execute<B: ChatBackend>(
         &self,
-        client: &Client<OpenAIConfig>,
+        backend: &B,
         model: &str,
         temperature: f32,
         messages: Vec<ChatCompletionRequestMessage>,
+        tools: &ToolRegistry,
     ) -> Result<CreateChatCompletionResponse> {
-        let request = self.create_request(model, temperature, messages)?;
+        let request = self.create_request(model, temperature, messages, tools)?;
         // 大模型根据调用工具的返回结果，继续规划下一步
-        let response = client.chat().create(request).await?;
+        let response = backend.chat(request).await?;
         Ok(response)
     }
 
+    /// 流式请求大模型，回复分片由调用方拼装
+    pub async fn execute_stream<B: ChatBackend>(
+        &self,
+        backend: &B,
+        model: &str,
+        temperature: f32,
+        messages: Vec<ChatCompletionRequestMessage>,
+        tools: &ToolRegistry,
+    ) -> Result<ChatStream> {
+        let mut request = self.create_request(model, temperature, messages, tools)?;
+        request.stream = Some(true);
+        // 在最后一个分片中返回本次请求的 token 用量
+        request.stream_options = Some(ChatCompletionStreamOptions {
+            include_usage: true,
+        });
+
+        backend.chat_stream(request).await
+    }
+
+    /// 请求大模型总结一段对话记录，请求中不包含工具
+    pub async fn summarize<B: ChatBackend>(
+        &self,
+        backend: &B,
+        model: &str,
+        language: &str,
+        messages: &[ChatCompletionRequestMessage],
+    ) -> Result<CreateChatCompletionResponse> {
+        let mut context = Context::new();
+        context.insert("language", language);
+        context.insert("transcript", &render_transcript(messages));
+
+        let content = self.engine.render("summarize.prompt", &context)?;
+        let user_message = ChatCompletionRequestUserMessageArgs::default()
+            .content(content)
+            .build()?;
+
+        let request = CreateChatCompletionRequestArgs::default()
+            .model(model)
+            // 总结需要忠于原文
+            .temperature(0.0)
+            .messages(vec![user_message.into()])
+            .build()?;
+
+        backend.chat(request).await
+    }
+
     fn create_request(
         &self,
         model: &str,
         temperature: f32,
         messages: Vec<ChatCompletionRequestMessage>,
+        tools: &ToolRegistry,
     ) -> Result<CreateChatCompletionRequest> {
-        let request = CreateChatCompletionRequestArgs::default()
+        let mut request = CreateChatCompletionRequestArgs::default();
+        request
             .model(model)
             .temperature(temperature)
-            .messages(messages)
-            .tools(Tools::list())
-            // 这里应设置为Required，强制大模型每次都调用工具，但是某些大模型不支持此选项
-            .tool_choice(ChatCompletionToolChoiceOption::Auto)
+            .messages(messages);
+
+        // 提示词模式下工具已写入系统消息，请求中不包含工具定义
+        if self.tool_mode == ToolMode::Native {
+            request
+                .tools(tools.definitions())
+                // 这里应设置为Required，强制大模型每次都调用工具，但是某些大模型不支持此选项
+                .tool_choice(ChatCompletionToolChoiceOption::Auto);
             // .response_format(ChatCompletionResponseFormat {
             //     r#type: ChatCompletionResponseFormatType::JsonObject,
             // })
-            .build()?;
+        }
+
+        Ok(request.build()?)
+    }
+}
 
-        Ok(request)
+/// 将消息转换为便于大模型阅读的纯文本记录
+fn render_transcript(messages: &[ChatCompletionRequestMessage]) -> String {
+    let mut lines = Vec::new();
+
+    for message in messages {
+        match message {
+            ChatCompletionRequestMessage::System(message) => {
+                lines.push(format!("[系统] {}", message.content));
+            }
+            ChatCompletionRequestMessage::User(message) => match &message.content {
+                ChatCompletionRequestUserMessageContent::Text(text) => {
+                    lines.push(format!("[用户] {}", text));
+                }
+                ChatCompletionRequestUserMessageContent::Array(_) => {
+                    lines.push("[用户] (非文本内容)".to_string());
+                }
+            },
+            ChatCompletionRequestMessage::Assistant(message) => {
+                if let Some(content) = message.content.as_ref().filter(|c| !c.is_empty()) {
+                    lines.push(format!("[助手] {}", content));
+                }
+                for tool_call in message.tool_calls.iter().flatten() {
+                    lines.push(format!(
+                        "[助手调用工具 {}] {}({})",
+                        tool_call.id, tool_call.function.name, tool_call.function.arguments
+                    ));
+                }
+            }
+            ChatCompletionRequestMessage::Tool(message) => {
+                lines.push(format!(
+                    "[工具结果 {}] {}",
+                    message.tool_call_id, message.content
+                ));
+            }
+            ChatCompletionRequestMessage::Function(message) => {
+                lines.push(format!(
+                    "[函数结果 {}] {}",
+                    message.name,
+                    message.content.as_deref().unwrap_or_default()
+                ));
+            }
+        }
     }
+
+    lines.join("\n")
 }
 
 #[cfg(test)]
@@ -146,4 +375,99 @@ mod tests {
         assert!(planning.engine.get_template("system.prompt").is_ok());
         Ok(())
     }
+
+    #[test]
+    fn test_build_system_message_with_previous_questions() -> Result<()> {
+        let planning = Planning::try_new()?;
+        let tools = ToolRegistry::builtin();
+
+        let system = planning
+            .build_system_message("问题", &[], &[], &tools, "chinese")?
+            .content;
+        assert!(system.contains("任务目标: 问题\n"));
+        assert!(!system.contains("此前已完成的任务"));
+
+        let previous = vec!["第一个问题".to_string(), "第二个问题".to_string()];
+        let system = planning
+            .build_system_message("再短一些", &previous, &[], &tools, "chinese")?
+            .content;
+        assert!(system.contains("任务目标: 再短一些\n"));
+        assert!(system.contains("- 第一个问题\n- 第二个问题\n"));
+        assert!(!system.contains("长期记忆中与当前任务相关"));
+
+        let memories = vec!["问题: 周杰伦的年龄\n答案: 45 岁".to_string()];
+        let system = planning
+            .build_system_message("周杰伦多高", &[], &memories, &tools, "chinese")?
+            .content;
+        assert!(system.contains("- 问题: 周杰伦的年龄\n答案: 45 岁\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_prompted_tool_mode() -> Result<()> {
+        let tools = ToolRegistry::builtin();
+
+        let native = Planning::try_new()?;
+        let system = native
+            .build_system_message("问题", &[], &[], &tools, "chinese")?
+            .content;
+        assert!(!system.contains("命令:"));
+        assert!(system.contains("通过tools的工具操作实现\n\n\n最佳实践"));
+        assert!(system.contains("想法：<内心的想法>"));
+        let request = native.create_request("mock-model", 0.3, Vec::new(), &tools)?;
+        assert_eq!(request.tools.map(|tools| tools.len()), Some(5));
+
+        // 提示词模式下工具作为命令写入系统消息，请求中不包含工具定义
+        let prompted = Planning::try_new()?.with_tool_mode(ToolMode::Prompted);
+        let system = prompted
+            .build_system_message("问题", &[], &[], &tools, "chinese")?
+            .content;
+        assert!(system.contains("命令:\n\n1. search: 通过搜索引擎搜索互联网上的内容。"));
+        assert!(system.contains(
+            r#"5. finish: 完成用户的任务目标
+   参数: {"properties":{"result":{"description":"最终结果","type":"string"}},"required":["result"],"type":"object"}"#
+        ));
+        assert!(system.contains("通过回复中的命令实现\n\n\n最佳实践"));
+        assert!(system.contains(r#""command": {"#));
+        let request = prompted.create_request("mock-model", 0.3, Vec::new(), &tools)?;
+        assert_eq!(request.tools, None);
+        assert_eq!(request.tool_choice, None);
+
+        let response = prompted.parse_response(
+            "```json\n{'command': {'name': 'search', 'args': {'query': 'Rust',}}}\n```",
+        )?;
+        assert_eq!(response.command.name, "search");
+        assert_eq!(response.command.args["query"], "Rust");
+        assert!(prompted.parse_response("我需要搜索一下").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_system_message_mentions_registered_memory_tools() -> Result<()> {
+        let planning = Planning::try_new()?;
+
+        let system = planning
+            .build_system_message("问题", &[], &[], &ToolRegistry::builtin(), "chinese")?
+            .content;
+        assert!(system.contains("立即使用 memory_save 将重要的信息保存到长期记忆中.\n"));
+        assert!(system.contains("使用 memory_search 检索长期记忆"));
+
+        let tools = ToolRegistry::builtin().without("memory_save");
+        let system = planning
+            .build_system_message("问题", &[], &[], &tools, "chinese")?
+            .content;
+        assert!(!system.contains("memory_save"));
+        assert!(system.contains("使用 memory_search 检索长期记忆"));
+
+        let tools = ToolRegistry::new();
+        let system = planning
+            .build_system_message("问题", &[], &[], &tools, "chinese")?
+            .content;
+        assert!(!system.contains("memory_"));
+        assert!(system.contains("语言.\n- 您必须始终独立做出决策"));
+
+        Ok(())
+    }
 }
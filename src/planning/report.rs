@@ -0,0 +1,360 @@
+use crate::{
+    agent::{Response, RunStatus, RunUsage, ToolMode},
+    history::{RunRecord, ToolCallRecord},
+    tools::{parse_lenient, ToolSource},
+};
+use async_openai::types::{ChatCompletionRequestMessage, ChatCompletionRequestUserMessageContent};
+use chrono::{Local, TimeZone};
+use serde::Serialize;
+use url::Url;
+
+/// 报告模版使用的运行记录
+#[derive(Debug, Serialize)]
+pub(crate) struct Report {
+    question: String,
+    status: &'static str,
+    model: String,
+    started_at: String,
+    duration: String,
+    usage: RunUsage,
+    answer: Option<String>,
+    error: Option<String>,
+    steps: Vec<ReportStep>,
+}
+
+/// 一条用户消息，或一次大模型回复及其工具调用
+#[derive(Debug, Serialize)]
+struct ReportStep {
+    role: &'static str,
+    // 大模型回复的序号，用户消息为 0
+    number: usize,
+    content: Option<String>,
+    tool_calls: Vec<ReportToolCall>,
+}
+
+#[derive(Debug, Serialize)]
+struct ReportToolCall {
+    id: String,
+    name: String,
+    arguments: String,
+    result: Option<String>,
+    error: Option<String>,
+    duration: Option<String>,
+    sources: Vec<ToolSource>,
+    // Markdown 代码块的围栏，比参数、结果和错误中最长的连续反引号更长
+    fence: String,
+}
+
+impl Report {
+    pub(crate) fn new(record: &RunRecord) -> Self {
+        let mut steps: Vec<ReportStep> = Vec::new();
+        // 提示词模式下命令按顺序记录，next_command 指向下一条未匹配的记录
+        let mut next_command = 0;
+        // 提示词模式下紧跟命令的用户消息是命令结果，已从运行记录中取得
+        let mut skip_command_result = false;
+
+        for message in &record.messages {
+            match message {
+                // 系统消息是固定的提示词，报告中只展示问题
+                ChatCompletionRequestMessage::System(_) => {}
+                ChatCompletionRequestMessage::User(_) if skip_command_result => {
+                    skip_command_result = false;
+                }
+                ChatCompletionRequestMessage::User(message) => steps.push(ReportStep {
+                    role: "user",
+                    number: 0,
+                    content: match &message.content {
+                        ChatCompletionRequestUserMessageContent::Text(text) => Some(text.clone()),
+                        ChatCompletionRequestUserMessageContent::Array(_) => {
+                            Some("(非文本内容)".to_string())
+                        }
+                    },
+                    tool_calls: Vec::new(),
+                }),
+                ChatCompletionRequestMessage::Assistant(message)
+                    if record.config.tool_mode == ToolMode::Prompted =>
+                {
+                    let content = message.content.clone().unwrap_or_default();
+                    let number = steps.iter().filter(|step| step.number > 0).count() + 1;
+
+                    let Ok(response) =
+                        parse_lenient(&content).and_then(serde_json::from_value::<Response>)
+                    else {
+                        steps.push(ReportStep {
+                            role: "assistant",
+                            number,
+                            content: Some(content).filter(|content| !content.is_empty()),
+                            tool_calls: Vec::new(),
+                        });
+                        continue;
+                    };
+
+                    let call = record.tool_calls[next_command..]
+                        .iter()
+                        .position(|call| {
+                            call.id.starts_with("command_") && call.name == response.command.name
+                        })
+                        .map(|offset| {
+                            let call = &record.tool_calls[next_command + offset];
+                            next_command += offset + 1;
+                            call
+                        });
+                    skip_command_result = call.is_some();
+
+                    let mut tool_call = report_tool_call(
+                        call.map(|call| call.id.clone()).unwrap_or_default(),
+                        response.command.name,
+                        &response.command.args.to_string(),
+                        call,
+                    );
+                    if tool_call.error.is_none() {
+                        tool_call.result = call.and_then(|call| call.result.clone());
+                    }
+
+                    let thoughts = response.thoughts;
+                    steps.push(ReportStep {
+                        role: "assistant",
+                        number,
+                        content: [thoughts.speak, thoughts.text]
+                            .into_iter()
+                            .find(|content| !content.is_empty()),
+                        tool_calls: vec![tool_call],
+                    });
+                }
+                ChatCompletionRequestMessage::Assistant(message) => steps.push(ReportStep {
+                    role: "assistant",
+                    number: steps.iter().filter(|step| step.number > 0).count() + 1,
+                    content: message
+                        .content
+                        .clone()
+                        .filter(|content| !content.is_empty()),
+                    tool_calls: message
+                        .tool_calls
+                        .iter()
+                        .flatten()
+                        .map(|tool_call| {
+                            report_tool_call(
+                                tool_call.id.clone(),
+                                tool_call.function.name.clone(),
+                                &tool_call.function.arguments,
+                                record
+                                    .tool_calls
+                                    .iter()
+                                    .rev()
+                                    .find(|call| call.id == tool_call.id),
+                            )
+                        })
+                        .collect(),
+                }),
+                ChatCompletionRequestMessage::Tool(message) => {
+                    let call = steps
+                        .iter_mut()
+                        .rev()
+                        .flat_map(|step| step.tool_calls.iter_mut())
+                        .find(|call| call.id == message.tool_call_id);
+
+                    // 失败的调用只展示错误信息
+                    if let Some(call) = call.filter(|call| call.error.is_none()) {
+                        call.result = Some(message.content.clone());
+                    }
+                }
+                ChatCompletionRequestMessage::Function(_) => {}
+            }
+        }
+
+        for call in steps.iter_mut().flat_map(|step| step.tool_calls.iter_mut()) {
+            call.fence = fence(&[
+                Some(call.arguments.as_str()),
+                call.result.as_deref(),
+                call.error.as_deref(),
+            ]);
+        }
+
+        Self {
+            question: record.question.clone(),
+            status: status_label(record.status),
+            model: record.config.model.clone(),
+            started_at: Local
+                .timestamp_millis_opt(record.started_at)
+                .single()
+                .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_default(),
+            duration: format_duration(record.finished_at - record.started_at),
+            usage: record.usage,
+            answer: record.answer.clone(),
+            error: record.error.clone(),
+            steps,
+        }
+    }
+}
+
+// 结果由调用方从对话记录中填入，错误、耗时和来源取自运行记录
+fn report_tool_call(
+    id: String,
+    name: String,
+    arguments: &str,
+    call: Option<&ToolCallRecord>,
+) -> ReportToolCall {
+    ReportToolCall {
+        id,
+        name,
+        arguments: pretty_json(arguments),
+        result: None,
+        error: call.and_then(|call| call.error.clone()),
+        duration: call.and_then(|call| {
+            call.finished_at
+                .map(|finished_at| format_duration(finished_at - call.started_at))
+        }),
+        sources: call
+            .filter(|call| call.error.is_none())
+            .map(|call| {
+                call.sources
+                    .iter()
+                    // 只有 http 和 https 链接可以放入报告，避免 javascript: 等链接
+                    .filter(|source| {
+                        Url::parse(&source.url)
+                            .is_ok_and(|url| matches!(url.scheme(), "http" | "https"))
+                    })
+                    .map(|source| ToolSource {
+                        // 标题换行会破坏报告中的链接
+                        title: source
+                            .title
+                            .split_whitespace()
+                            .collect::<Vec<_>>()
+                            .join(" "),
+                        url: source.url.clone(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+        fence: String::new(),
+    }
+}
+
+fn fence(contents: &[Option<&str>]) -> String {
+    let longest = contents
+        .iter()
+        .flatten()
+        .flat_map(|content| content.split(|c| c != '`'))
+        .map(str::len)
+        .max()
+        .unwrap_or_default();
+
+    "`".repeat(longest.max(2) + 1)
+}
+
+fn status_label(status: RunStatus) -> &'static str {
+    match status {
+        RunStatus::Completed => "已完成",
+        RunStatus::StepLimit => "达到最大调用轮数",
+        RunStatus::Budget => "预算用尽",
+        RunStatus::Cancelled => "已取消",
+        RunStatus::Error => "出错",
+    }
+}
+
+fn format_duration(millis: i64) -> String {
+    format!("{:.1} 秒", millis.max(0) as f64 / 1000.0)
+}
+
+// 参数是合法的 JSON 时格式化，便于阅读
+fn pretty_json(arguments: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(arguments)
+        .and_then(|value| serde_json::to_string_pretty(&value))
+        .unwrap_or_else(|_| arguments.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::ReActAgentConfig;
+    use anyhow::Result;
+    use async_openai::types::{
+        ChatCompletionRequestAssistantMessageArgs, ChatCompletionRequestUserMessageArgs,
+    };
+
+    fn record(
+        tool_mode: ToolMode,
+        messages: Vec<ChatCompletionRequestMessage>,
+    ) -> Result<RunRecord> {
+        let mut config = ReActAgentConfig::builder()
+            .set_api_key("my_api_key")
+            .set_model("mock-model")
+            .try_set_base_url("http://localhost")?
+            .build()?;
+        config.tool_mode = tool_mode;
+
+        Ok(RunRecord {
+            id: 1,
+            question: "Context Caching 是什么？".to_string(),
+            config,
+            status: RunStatus::Completed,
+            answer: Some("一种缓存技术".to_string()),
+            error: None,
+            started_at: 1_700_000_000_000,
+            finished_at: 1_700_000_003_500,
+            usage: RunUsage::default(),
+            messages,
+            tool_calls: vec![ToolCallRecord {
+                id: "command_1".to_string(),
+                name: "search".to_string(),
+                arguments: r#"{"query":"Context Caching"}"#.to_string(),
+                result: Some("缓存技术".to_string()),
+                error: None,
+                started_at: 1_700_000_001_000,
+                finished_at: Some(1_700_000_002_200),
+                sources: vec![ToolSource {
+                    title: "Kimi ](文档)\n第二行".to_string(),
+                    url: "https://example.com/docs".to_string(),
+                }],
+            }],
+        })
+    }
+
+    #[test]
+    fn test_report_prompted_tool_calls() -> Result<()> {
+        let record = record(
+            ToolMode::Prompted,
+            vec![
+                ChatCompletionRequestAssistantMessageArgs::default()
+                    .content(r#"{"thoughts": {"speak": "需要先搜索"}, "command": {"name": "search", "args": {"query": "Context Caching"}}}"#)
+                    .build()?
+                    .into(),
+                ChatCompletionRequestUserMessageArgs::default()
+                    .content("命令 search 的执行结果:\n\n缓存技术")
+                    .build()?
+                    .into(),
+                ChatCompletionRequestAssistantMessageArgs::default()
+                    .content("不是 JSON 的回复")
+                    .build()?
+                    .into(),
+            ],
+        )?;
+
+        let report = Report::new(&record);
+
+        // 命令结果不作为用户消息展示
+        assert_eq!(report.steps.len(), 2);
+        assert_eq!(report.steps[0].number, 1);
+        assert_eq!(report.steps[0].content.as_deref(), Some("需要先搜索"));
+
+        let call = &report.steps[0].tool_calls[0];
+        assert_eq!(call.id, "command_1");
+        assert_eq!(call.name, "search");
+        assert_eq!(call.arguments, "{\n  \"query\": \"Context Caching\"\n}");
+        assert_eq!(call.result.as_deref(), Some("缓存技术"));
+        assert_eq!(call.duration.as_deref(), Some("1.2 秒"));
+        assert_eq!(
+            call.sources,
+            vec![ToolSource {
+                title: "Kimi ](文档) 第二行".to_string(),
+                url: "https://example.com/docs".to_string(),
+            }]
+        );
+
+        assert_eq!(report.steps[1].content.as_deref(), Some("不是 JSON 的回复"));
+        assert!(report.steps[1].tool_calls.is_empty());
+
+        Ok(())
+    }
+}
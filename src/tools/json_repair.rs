@@ -0,0 +1,291 @@
+use serde_json::{Number, Value};
+use std::{iter::Peekable, str::Chars};
+
+/// 解析大模型输出的 JSON，严格解析失败时先修复常见的格式问题再解析
+///
+/// 修复后仍然无法解析时返回严格解析的错误
+pub fn parse_lenient(text: &str) -> serde_json::Result<Value> {
+    serde_json::from_str(text).or_else(|e| serde_json::from_str(&repair_json(text)).map_err(|_| e))
+}
+
+/// 修复较小的模型或非 OpenAI 模型常见的 JSON 格式问题：
+///
+/// - markdown 代码块包裹，或 JSON 前后带有说明文字
+/// - 单引号字符串、没有引号的键、Python 风格的 True/False/None
+/// - 不合法的数字写法，例如 +1、.5，以及 NaN、Infinity（修复为 null）
+/// - 字符串中未转义的换行、制表符等控制字符
+/// - 对象和数组末尾多余的逗号
+/// - 输出被截断导致缺少的引号和括号
+pub fn repair_json(text: &str) -> String {
+    // 从每个可能的起点修复，使用第一个修复后合法的结果，都不合法时使用第一个起点的结果
+    let mut first = None;
+    for start in json_starts(text) {
+        let repaired = repair_from(&text[start..]);
+        if serde_json::from_str::<Value>(&repaired).is_ok() {
+            return repaired;
+        }
+        first.get_or_insert(repaired);
+    }
+
+    // 没有对象或数组时不是 JSON，原样返回
+    first.unwrap_or_else(|| text.to_string())
+}
+
+// 从 JSON 的起点开始修复，最外层的括号闭合后忽略剩下的内容
+fn repair_from(text: &str) -> String {
+    let mut repaired = String::with_capacity(text.len());
+    // 尚未闭合的括号
+    let mut closers = Vec::new();
+    // 当前所在字符串的引号，不在字符串中时为 None
+    let mut quote: Option<char> = None;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if let Some(q) = quote {
+            match c {
+                '\\' => match chars.next() {
+                    // 单引号字符串中的 \' 在 JSON 中不需要转义
+                    Some('\'') => repaired.push('\''),
+                    Some(escaped) => {
+                        repaired.push('\\');
+                        repaired.push(escaped);
+                    }
+                    None => {}
+                },
+                c if c == q => {
+                    repaired.push('"');
+                    quote = None;
+                }
+                '"' => repaired.push_str("\\\""),
+                '\n' => repaired.push_str("\\n"),
+                '\r' => repaired.push_str("\\r"),
+                '\t' => repaired.push_str("\\t"),
+                c if c.is_control() => repaired.push_str(&format!("\\u{:04x}", c as u32)),
+                c => repaired.push(c),
+            }
+            continue;
+        }
+
+        match c {
+            '"' | '\'' => {
+                repaired.push('"');
+                quote = Some(c);
+            }
+            '{' => {
+                repaired.push(c);
+                closers.push('}');
+            }
+            '[' => {
+                repaired.push(c);
+                closers.push(']');
+            }
+            '}' | ']' => {
+                trim_trailing_comma(&mut repaired);
+                repaired.push(c);
+                closers.pop();
+
+                // 最外层的括号闭合后剩下的是代码块标记或说明文字
+                if closers.is_empty() {
+                    break;
+                }
+            }
+            c if c.is_ascii_digit() || matches!(c, '-' | '+' | '.') => {
+                let mut number = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if !(next.is_ascii_digit() || matches!(next, '-' | '+' | '.' | 'e' | 'E')) {
+                        break;
+                    }
+                    number.push(next);
+                    chars.next();
+                }
+
+                // -Infinity
+                if matches!(number.as_str(), "-" | "+") {
+                    if let Some(&next) = chars.peek().filter(|next| next.is_alphabetic()) {
+                        chars.next();
+                        let word = read_word(next, &mut chars);
+                        if is_non_finite(&word) {
+                            repaired.push_str("null");
+                        } else {
+                            push_string(&mut repaired, &(number + &word));
+                        }
+                        continue;
+                    }
+                }
+
+                push_number(&mut repaired, &number);
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let word = read_word(c, &mut chars);
+
+                match word.as_str() {
+                    "true" | "false" | "null" => repaired.push_str(&word),
+                    "True" => repaired.push_str("true"),
+                    "False" => repaired.push_str("false"),
+                    "None" => repaired.push_str("null"),
+                    // JSON 中没有非有限的数字
+                    word if is_non_finite(word) => repaired.push_str("null"),
+                    // 没有引号的键或值
+                    _ => push_string(&mut repaired, &word),
+                }
+            }
+            c => repaired.push(c),
+        }
+    }
+
+    // 输出被截断时补齐引号和括号
+    if quote.is_some() {
+        repaired.push('"');
+    }
+    while let Some(closer) = closers.pop() {
+        trim_trailing_comma(&mut repaired);
+        repaired.push(closer);
+    }
+
+    repaired
+}
+
+fn read_word(first: char, chars: &mut Peekable<Chars>) -> String {
+    let mut word = first.to_string();
+    while let Some(&next) = chars.peek() {
+        if !(next.is_alphanumeric() || next == '_') {
+            break;
+        }
+        word.push(next);
+        chars.next();
+    }
+
+    word
+}
+
+fn is_non_finite(word: &str) -> bool {
+    matches!(word, "NaN" | "Infinity" | "inf")
+}
+
+fn push_string(repaired: &mut String, value: &str) {
+    repaired.push('"');
+    repaired.push_str(value);
+    repaired.push('"');
+}
+
+// 修复 +1、.5、5. 这样的写法，仍然不是合法数字的作为字符串，例如没有引号的日期
+fn push_number(repaired: &mut String, number: &str) {
+    let (sign, digits) = match number.strip_prefix(['-', '+']) {
+        Some(digits) if number.starts_with('-') => ("-", digits),
+        Some(digits) => ("", digits),
+        None => ("", number),
+    };
+    let digits = digits.strip_suffix('.').unwrap_or(digits);
+    let normalized = match digits.strip_prefix('.') {
+        Some(fraction) => format!("{}0.{}", sign, fraction),
+        None => format!("{}{}", sign, digits),
+    };
+
+    if serde_json::from_str::<Number>(&normalized).is_ok() {
+        repaired.push_str(&normalized);
+    } else {
+        push_string(repaired, number);
+    }
+}
+
+// 可能的 JSON 起点，跳过代码块标记和前面的说明文字
+//
+// 工具参数和提示词模式的回复都是对象，因此先尝试每个 {，再尝试 [，
+// 避免说明文字中 [1] 这样的引用被当作 JSON
+fn json_starts(text: &str) -> impl Iterator<Item = usize> + '_ {
+    text.match_indices('{')
+        .chain(text.match_indices('['))
+        .map(|(start, _)| start)
+}
+
+fn trim_trailing_comma(repaired: &mut String) {
+    let trimmed = repaired.trim_end();
+    if let Some(without_comma) = trimmed.strip_suffix(',') {
+        repaired.truncate(without_comma.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_lenient() -> serde_json::Result<()> {
+        let cases = [
+            (r#"{"query": "Rust"}"#, json!({ "query": "Rust" })),
+            // 多余的逗号
+            (
+                r#"{"tags": ["a", "b",], "top_k": 3,}"#,
+                json!({ "tags": ["a", "b"], "top_k": 3 }),
+            ),
+            // 单引号和字符串中的双引号
+            (
+                r#"{'content': 'He said "hi", it\'s ok'}"#,
+                json!({ "content": r#"He said "hi", it's ok"# }),
+            ),
+            // 字符串中未转义的换行和制表符
+            (
+                "{\"filename\": \"a.md\", \"content\": \"# 标题\n\t正文\"}",
+                json!({ "filename": "a.md", "content": "# 标题\n\t正文" }),
+            ),
+            // markdown 代码块
+            (
+                "```json\n{\"query\": \"周杰伦\"}\n```",
+                json!({ "query": "周杰伦" }),
+            ),
+            // 前后的说明文字
+            (
+                "参数如下：{\"query\": \"周杰伦\"} 请执行",
+                json!({ "query": "周杰伦" }),
+            ),
+            // 说明文字中的引用不是参数
+            ("参考[1]：{\"query\": \"Rust\"}", json!({ "query": "Rust" })),
+            // 说明文字中的括号无法修复为 JSON 时尝试下一个起点
+            (
+                "结果{见下文}：{'query': 'Rust'}",
+                json!({ "query": "Rust" }),
+            ),
+            // 顶层的数组
+            ("```json\n[1, 2,]\n```", json!([1, 2])),
+            // 没有引号的键和 Python 风格的字面量
+            (
+                "{query: 'Rust', exact: True, top_k: None}",
+                json!({ "query": "Rust", "exact": true, "top_k": null }),
+            ),
+            // 科学计数法、不合法的数字写法和非有限的数字
+            (
+                "{a: 1e5, b: -2.5E-3, c: +1, d: .5, e: 5., f: NaN, g: -Infinity, h: Infinity}",
+                json!({ "a": 1e5, "b": -2.5e-3, "c": 1, "d": 0.5, "e": 5, "f": null, "g": null, "h": null }),
+            ),
+            // 没有引号的日期仍然是字符串
+            (
+                "{date: 2024-01-01, top_k: 3}",
+                json!({ "date": "2024-01-01", "top_k": 3 }),
+            ),
+            // 被截断的输出
+            (
+                r#"{"filter": {"lang": "zh"}, "tags": ["a", "b"#,
+                json!({ "filter": { "lang": "zh" }, "tags": ["a", "b"] }),
+            ),
+        ];
+
+        for (text, expected) in cases {
+            assert_eq!(parse_lenient(text)?, expected, "{}", text);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_lenient_keeps_valid_strings() -> serde_json::Result<()> {
+        // 合法 JSON 中的转义、逗号和括号不受影响
+        let text = r#"{"content": "a,}\" \\n ]", "url": "https://example.com/a,b"}"#;
+        assert_eq!(repair_json(text), text);
+
+        assert!(parse_lenient(r#"{"query": }"#).is_err());
+        assert_eq!(repair_json("我需要先查一下天气"), "我需要先查一下天气");
+
+        Ok(())
+    }
+}
@@ -1,12 +1,32 @@
+mod json_repair;
 pub mod search;
 // mod tool_code_interpreter;
 // mod tool_file_append;
+mod tool_error;
 mod tool_file_write;
 mod tool_finish;
+mod tool_memory_save;
+mod tool_memory_search;
+mod tool_output;
+mod tool_registry;
+mod tool_runner;
+mod tool_schema;
 mod tool_search;
 mod tool_traits;
-#[allow(clippy::module_inception)]
-mod tools;
+mod tool_validation;
 
-pub(crate) use tool_traits::{ToolExector, ToolPrompt};
-pub(crate) use tools::Tools;
+pub use json_repair::{parse_lenient, repair_json};
+pub use my_agent_derive::Tool;
+pub use tool_error::{ToolError, ToolErrorKind};
+pub use tool_file_write::{FileWrite, FileWriteArgs};
+pub use tool_finish::{Finish, FinishArgs};
+pub use tool_memory_save::{MemorySave, MemorySaveArgs};
+pub use tool_memory_search::{MemorySearch, MemorySearchArgs};
+pub use tool_output::{ToolOutput, ToolSource};
+pub use tool_registry::ToolRegistry;
+pub(crate) use tool_runner::{run_tool_calls, ToolCall, ToolOutcome};
+#[doc(hidden)]
+pub use tool_schema::__private;
+pub use tool_schema::ToolParameter;
+pub use tool_search::{Search, SearchArgs};
+pub use tool_traits::{parse_arguments, Tool, ToolArgs, TypedTool};
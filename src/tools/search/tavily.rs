@@ -1,14 +1,22 @@
+use crate::cassette::{Cassette, CassetteMode};
 use anyhow::Result;
 use derive_builder::Builder;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use std::fmt::{self, Display};
+use serde_json::Value;
+use std::{
+    fmt::{self, Display},
+    sync::Arc,
+};
+
+const CASSETTE_KIND: &str = "tavily";
 
 #[derive(Debug)]
 pub struct Tavily {
     api_key: String,
     base_url: String,
     client: Client,
+    cassette: Option<Arc<Cassette>>,
 }
 
 impl Tavily {
@@ -17,9 +25,16 @@ impl Tavily {
             api_key: api_key.into(),
             base_url: "https://api.tavily.com".to_string(),
             client: Client::new(),
+            cassette: None,
         }
     }
 
+    /// 使用磁带录制或回放搜索请求
+    pub fn with_cassette(mut self, cassette: Arc<Cassette>) -> Self {
+        self.cassette = Some(cassette);
+        self
+    }
+
     pub async fn search(&self, params: SearchParameters) -> Result<SearchResponse> {
         let params = SearchParameters {
             api_key: self.api_key.clone(),
@@ -27,10 +42,27 @@ impl Tavily {
             ..params
         };
 
+        let Some(cassette) = &self.cassette else {
+            return self.send(&params).await;
+        };
+
+        let request = cassette_request(&params)?;
+
+        match cassette.mode() {
+            CassetteMode::Replay => cassette.replay_interaction(CASSETTE_KIND, &request),
+            CassetteMode::Record => {
+                let response = self.send(&params).await?;
+                cassette.record_interaction(CASSETTE_KIND, &request, &response)?;
+                Ok(response)
+            }
+        }
+    }
+
+    async fn send(&self, params: &SearchParameters) -> Result<SearchResponse> {
         let response = self
             .client
             .post(format!("{}/search", self.base_url))
-            .json(&params)
+            .json(params)
             .send()
             .await?
             .json()
@@ -40,6 +72,16 @@ impl Tavily {
     }
 }
 
+/// 写入磁带的请求内容，去掉 api_key，避免密钥泄露到磁带文件，也不影响请求哈希
+pub(crate) fn cassette_request(params: &SearchParameters) -> Result<Value> {
+    let mut request = serde_json::to_value(params)?;
+    if let Some(request) = request.as_object_mut() {
+        request.remove("api_key");
+    }
+
+    Ok(request)
+}
+
 #[derive(Debug, Default, Builder, Serialize, Deserialize)]
 #[builder(setter(into, strip_option), default)]
 pub struct SearchParameters {
@@ -221,4 +263,51 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_search_replay_from_cassette() -> anyhow::Result<()> {
+        let path = std::env::temp_dir().join(format!(
+            "my-agent-tavily-cassette-{}.json",
+            std::process::id()
+        ));
+
+        let recorded = SearchParameters {
+            api_key: "recorded_api_key".to_string(),
+            max_results: Some(1),
+            ..SearchParameters::builder()
+                .query("Context Caching")
+                .build()?
+        };
+        let response = SearchResponse {
+            answer: None,
+            query: Some("Context Caching".to_string()),
+            response_time: Some(0.5),
+            images: vec![],
+            results: vec![SearchItem {
+                title: "Context Caching".to_string(),
+                url: "https://example.com".to_string(),
+                content: "Context Caching 是一种缓存技术".to_string(),
+                raw_content: None,
+                score: 0.9,
+            }],
+        };
+
+        let cassette = Cassette::record(&path)?;
+        cassette.record_interaction(CASSETTE_KIND, &cassette_request(&recorded)?, &response)?;
+        assert!(!std::fs::read_to_string(&path)?.contains("recorded_api_key"));
+
+        // 回放时使用不同的 api_key 也能命中同一条记录
+        let tavily =
+            Tavily::new("another_api_key").with_cassette(Arc::new(Cassette::replay(&path)?));
+        let params = SearchParameters::builder()
+            .query("Context Caching")
+            .build()?;
+        let replayed = tavily.search(params).await?;
+
+        assert_eq!(replayed.results.len(), 1);
+        assert_eq!(format!("{}", replayed), "Context Caching 是一种缓存技术\n");
+
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
 }
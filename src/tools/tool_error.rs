@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{
+    fmt::{self, Display},
+    time::Duration,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolErrorKind {
+    /// 大模型调用了不存在的工具
+    UnknownTool,
+    /// 工具参数不是合法的 JSON 或不符合工具定义
+    InvalidArguments,
+    /// 工具执行失败，例如网络请求出错
+    ExecutionFailed,
+    /// 工具执行超时
+    Timeout,
+    /// 运行被取消，工具没有执行完成
+    Cancelled,
+}
+
+/// 工具调用失败的原因，会作为工具消息返回给大模型，让它修正调用方式或换一种方案
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolError {
+    pub kind: ToolErrorKind,
+    pub tool: String,
+    pub message: String,
+}
+
+impl ToolError {
+    pub fn new(kind: ToolErrorKind, tool: impl Into<String>, message: impl Display) -> Self {
+        Self {
+            kind,
+            tool: tool.into(),
+            message: message.to_string(),
+        }
+    }
+
+    pub fn unknown_tool(tool: impl Into<String>) -> Self {
+        let tool = tool.into();
+        let message = format!("Unknown tool: {}", tool);
+        Self::new(ToolErrorKind::UnknownTool, tool, message)
+    }
+
+    pub fn invalid_arguments(tool: impl Into<String>, message: impl Display) -> Self {
+        Self::new(ToolErrorKind::InvalidArguments, tool, message)
+    }
+
+    pub fn execution_failed(tool: impl Into<String>, message: impl Display) -> Self {
+        Self::new(ToolErrorKind::ExecutionFailed, tool, message)
+    }
+
+    pub fn timeout(tool: impl Into<String>, timeout: Duration) -> Self {
+        let message = format!("Tool execution timed out after {:?}", timeout);
+        Self::new(ToolErrorKind::Timeout, tool, message)
+    }
+
+    pub fn cancelled(tool: impl Into<String>) -> Self {
+        Self::new(ToolErrorKind::Cancelled, tool, "Tool call was cancelled")
+    }
+
+    /// 作为工具消息内容的 JSON 错误信息
+    pub fn to_content(&self) -> String {
+        json!({ "error": self }).to_string()
+    }
+}
+
+impl Display for ToolError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ToolError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_error_content() -> anyhow::Result<()> {
+        let error = ToolError::invalid_arguments("search", "missing field `query`");
+        let content: serde_json::Value = serde_json::from_str(&error.to_content())?;
+
+        assert_eq!(content["error"]["kind"], "invalid_arguments");
+        assert_eq!(content["error"]["tool"], "search");
+        assert_eq!(content["error"]["message"], "missing field `query`");
+
+        let error = ToolError::unknown_tool("unknown");
+        assert_eq!(error.kind, ToolErrorKind::UnknownTool);
+        assert_eq!(error.to_string(), "Unknown tool: unknown");
+
+        let error = ToolError::timeout("search", Duration::from_secs(30));
+        assert_eq!(error.kind, ToolErrorKind::Timeout);
+        assert_eq!(error.to_string(), "Tool execution timed out after 30s");
+
+        Ok(())
+    }
+}
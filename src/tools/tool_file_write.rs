@@ -1,139 +1,68 @@
-use super::{ToolExector, ToolPrompt};
-use anyhow::{anyhow, Result};
-use async_openai::{
-    error::OpenAIError,
-    types::{
-        ChatCompletionTool, ChatCompletionToolArgs, ChatCompletionToolType, FunctionCall,
-        FunctionObjectArgs,
-    },
-};
+use super::{Tool, TypedTool};
+use anyhow::Result;
+use futures::future::BoxFuture;
 use serde::{Deserialize, Serialize};
-use serde_json::json;
-use std::{
-    fmt::{self, Debug},
-    path::Path,
-};
+use std::path::Path;
 use tokio::{
     fs::{self, File},
     io::AsyncWriteExt,
 };
 
-#[derive(Default)]
-pub struct FileWrite {
-    filename: String,
-    content: String,
-}
-
-impl FileWrite {
-    pub fn new(filename: String, content: String) -> Self {
-        FileWrite { filename, content }
-    }
-}
-
-impl ToolExector for FileWrite {
-    async fn execute(&self) -> Result<String> {
-        let path = Path::new(".").join("output");
-        fs::create_dir_all(&path).await?;
-        let mut file = File::create(path.join(&self.filename)).await?;
-        file.write_all(self.content.as_bytes()).await?;
-        Ok("写入成功".to_string())
-    }
-}
-
-impl ToolPrompt for FileWrite {
-    fn command(&self) -> String {
-        r#"{
-            "name": "file_write",
-            "description": "文件写入工具：用于将内容写入文件，将覆盖原有内容",
-            "args": [
-                {
-                    "name": "filename",
-                    "type": "string",
-                    "description": "文件名称，请保证文件名称的唯一性"
-                },
-                {
-                    "name": "content",
-                    "type": "string",
-                    "description": "文件内容"
-                }
-            ]
-        }"#
-        .to_string()
-    }
+/// 文件写入工具：将内容写入工作目录 output 下的文件
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FileWrite;
 
-    fn resource(&self) -> String {
-        "将内容写入文件".to_string()
-    }
-}
-
-impl Debug for FileWrite {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("FileWrite")
-            .field("filename", &self.filename)
-            .field("content", &"...")
-            .finish()
-    }
+/// 文件写入工具：用于将内容写入文件，将覆盖原有内容
+#[derive(Serialize, Deserialize, Tool)]
+pub struct FileWriteArgs {
+    /// 文件名称，请保证文件名称的唯一性
+    #[tool(min_length = 1, max_length = 255)]
+    pub filename: String,
+    /// 文件内容
+    pub content: String,
 }
 
-impl TryFrom<FileWrite> for ChatCompletionTool {
-    type Error = OpenAIError;
+impl TypedTool for FileWrite {
+    type Args = FileWriteArgs;
 
-    fn try_from(_file_write: FileWrite) -> Result<Self, Self::Error> {
-        ChatCompletionToolArgs::default()
-            .r#type(ChatCompletionToolType::Function)
-            .function(
-                FunctionObjectArgs::default()
-                    .name("file_write")
-                    .description("文件写入工具：用于将内容写入文件，将覆盖原有内容")
-                    .parameters(json!({
-                        "type": "object",
-                        "properties": {
-                            "filename": {
-                                "type": "string",
-                                "description": "文件名称，请保证文件名称的唯一性",
-                            },
-                            "content": {
-                                "type": "string",
-                                "description": "文件内容",
-                            },
-                        },
-                        "required": ["filename", "content"],
-                    }))
-                    .build()?,
-            )
-            .build()
+    fn call(&self, args: FileWriteArgs) -> BoxFuture<'_, Result<String>> {
+        Box::pin(async move {
+            let path = Path::new(".").join("output");
+            fs::create_dir_all(&path).await?;
+            let mut file = File::create(path.join(&args.filename)).await?;
+            file.write_all(args.content.as_bytes()).await?;
+            // tokio 的 File 在后台完成写入，返回前需要等待写入结束
+            file.flush().await?;
+            Ok("写入成功".to_string())
+        })
     }
-}
-
-#[derive(Serialize, Deserialize)]
-struct FileWriteArgs {
-    filename: String,
-    content: String,
-}
 
-impl TryFrom<FunctionCall> for FileWrite {
-    type Error = anyhow::Error;
-
-    fn try_from(call: FunctionCall) -> Result<Self, Self::Error> {
-        if call.name == "file_write" {
-            let args: FileWriteArgs = serde_json::from_str(&call.arguments)?;
-            Ok(FileWrite::new(args.filename, args.content))
-        } else {
-            Err(anyhow!("Invalid function call: {:?}", call))
-        }
+    // 多次写入同一个文件时必须按调用顺序执行
+    fn is_concurrent_safe(&self) -> bool {
+        false
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tools::{ToolError, ToolErrorKind};
+    use serde_json::json;
 
     #[tokio::test]
     async fn test_file_write() -> Result<()> {
-        let file_write = FileWrite::new("test.txt".to_string(), "test content 1".to_string());
-        let result = file_write.execute().await?;
+        let arguments = json!({ "filename": "test.txt", "content": "test content 1" });
+        let result = FileWrite.execute(arguments).await?;
         assert_eq!(result, "写入成功");
 
+        // 缺少参数时返回参数错误
+        let error = FileWrite
+            .execute(json!({ "filename": "test.txt" }))
+            .await
+            .unwrap_err()
+            .downcast::<ToolError>()?;
+        assert_eq!(error.kind, ToolErrorKind::InvalidArguments);
+
         Ok(())
     }
 }
@@ -1,102 +1,23 @@
-use super::{ToolExector, ToolPrompt};
-use anyhow::{anyhow, Result};
-use async_openai::{
-    error::OpenAIError,
-    types::{
-        ChatCompletionTool, ChatCompletionToolArgs, ChatCompletionToolType, FunctionCall,
-        FunctionObjectArgs,
-    },
-};
+use super::{Tool, TypedTool};
+use anyhow::Result;
+use futures::future::BoxFuture;
 use serde::{Deserialize, Serialize};
-use serde_json::json;
-use std::fmt::{self, Debug};
 
-#[derive(Default)]
-pub struct Finish {
-    result: String,
-}
-
-impl Finish {
-    pub fn new(result: String) -> Self {
-        Self { result }
-    }
-}
-
-impl ToolExector for Finish {
-    async fn execute(&self) -> Result<String> {
-        Ok(self.result.clone())
-    }
-}
-
-impl ToolPrompt for Finish {
-    fn command(&self) -> String {
-        r#"{
-            "name": "finish",
-            "description": "完成用户的任务目标",
-            "args": [
-                {
-                    "name": "result",
-                    "type": "string",
-                    "description": "最终结果"
-                }
-            ]
-        }"#
-        .to_string()
-    }
-
-    fn resource(&self) -> String {
-        "完成用户的任务目标".to_string()
-    }
-}
-
-impl Debug for Finish {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("Finish")
-            .field("result", &self.result)
-            .finish()
-    }
-}
-
-impl TryFrom<Finish> for ChatCompletionTool {
-    type Error = OpenAIError;
-
-    fn try_from(_finish: Finish) -> Result<Self, Self::Error> {
-        ChatCompletionToolArgs::default()
-            .r#type(ChatCompletionToolType::Function)
-            .function(
-                FunctionObjectArgs::default()
-                    .name("finish")
-                    .description("完成用户的任务目标")
-                    .parameters(json!({
-                        "type": "object",
-                        "properties": {
-                            "result": {
-                                "type": "string",
-                                "description": "最终结果",
-                            }
-                        },
-                        "required": ["result"],
-                    }))
-                    .build()?,
-            )
-            .build()
-    }
-}
+/// 结束工具：大模型调用它给出最终答案，Agent 随之结束运行
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Finish;
 
-#[derive(Serialize, Deserialize)]
-struct FinishArgs {
-    result: String,
+/// 完成用户的任务目标
+#[derive(Serialize, Deserialize, Tool)]
+pub struct FinishArgs {
+    /// 最终结果
+    pub result: String,
 }
 
-impl TryFrom<FunctionCall> for Finish {
-    type Error = anyhow::Error;
+impl TypedTool for Finish {
+    type Args = FinishArgs;
 
-    fn try_from(call: FunctionCall) -> Result<Self, Self::Error> {
-        if call.name == "finish" {
-            let args: FinishArgs = serde_json::from_str(&call.arguments)?;
-            Ok(Finish::new(args.result))
-        } else {
-            Err(anyhow!("Invalid function call: {:?}", call))
-        }
+    fn call(&self, args: FinishArgs) -> BoxFuture<'_, Result<String>> {
+        Box::pin(async move { Ok(args.result) })
     }
 }
@@ -0,0 +1,107 @@
+use super::{Tool, TypedTool};
+use crate::memory::{HashEmbedder, LongMemory, MemoryKind};
+use anyhow::Result;
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{self, Debug},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// 记忆工具读写的长期记忆
+#[derive(Clone)]
+pub(super) enum MemoryStore {
+    /// 每次调用时打开的本地文件
+    File(PathBuf),
+    /// 与 Agent 共用的长期记忆，Agent 检索的记忆与工具保存的记忆一致
+    Shared(Arc<LongMemory>),
+}
+
+impl MemoryStore {
+    pub(super) fn open(&self) -> Result<Arc<LongMemory>> {
+        match self {
+            MemoryStore::File(path) => Ok(Arc::new(LongMemory::open(
+                path,
+                Arc::new(HashEmbedder::default()),
+            )?)),
+            MemoryStore::Shared(long_memory) => Ok(long_memory.clone()),
+        }
+    }
+}
+
+impl Default for MemoryStore {
+    // 与文件写入工具位于同一个工作目录
+    fn default() -> Self {
+        MemoryStore::File(Path::new(".").join("output").join("memory.json"))
+    }
+}
+
+impl Debug for MemoryStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MemoryStore::File(path) => f.debug_tuple("File").field(path).finish(),
+            MemoryStore::Shared(_) => f.write_str("Shared"),
+        }
+    }
+}
+
+/// 记忆保存工具：将关键事实保存到长期记忆中
+#[derive(Debug, Clone)]
+pub struct MemorySave {
+    store: MemoryStore,
+}
+
+impl MemorySave {
+    pub fn new() -> Self {
+        Self {
+            store: MemoryStore::default(),
+        }
+    }
+
+    /// 使用指定的存储文件，默认为工作目录下的 output/memory.json
+    pub fn with_store(mut self, store: impl Into<PathBuf>) -> Self {
+        self.store = MemoryStore::File(store.into());
+        self
+    }
+
+    /// 使用已打开的长期记忆，通常与 Agent 的长期记忆相同
+    pub fn with_long_memory(mut self, long_memory: Arc<LongMemory>) -> Self {
+        self.store = MemoryStore::Shared(long_memory);
+        self
+    }
+}
+
+impl Default for MemorySave {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 记忆保存工具：将关键事实保存到长期记忆中，之后可以通过 memory_search 检索
+#[derive(Serialize, Deserialize, Tool)]
+pub struct MemorySaveArgs {
+    /// 需要记住的内容，应当是完整、独立的一句话
+    pub content: String,
+}
+
+impl TypedTool for MemorySave {
+    type Args = MemorySaveArgs;
+
+    fn call(&self, args: MemorySaveArgs) -> BoxFuture<'_, Result<String>> {
+        Box::pin(async move {
+            let entry = self
+                .store
+                .open()?
+                .add(MemoryKind::Note, &args.content)
+                .await?;
+
+            Ok(format!("保存成功，记忆编号 {}", entry.id))
+        })
+    }
+
+    // 每次保存都会重写存储文件，必须按调用顺序执行
+    fn is_concurrent_safe(&self) -> bool {
+        false
+    }
+}
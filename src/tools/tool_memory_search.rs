@@ -0,0 +1,115 @@
+use super::{tool_memory_save::MemoryStore, Tool, TypedTool};
+use crate::memory::LongMemory;
+use anyhow::Result;
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, sync::Arc};
+
+const DEFAULT_TOP_K: usize = 3;
+const MAX_TOP_K: usize = 10;
+
+/// 记忆检索工具：从长期记忆中搜索与查询相关的内容
+#[derive(Debug, Clone)]
+pub struct MemorySearch {
+    store: MemoryStore,
+}
+
+impl MemorySearch {
+    pub fn new() -> Self {
+        Self {
+            store: MemoryStore::default(),
+        }
+    }
+
+    /// 使用指定的存储文件，默认为工作目录下的 output/memory.json
+    pub fn with_store(mut self, store: impl Into<PathBuf>) -> Self {
+        self.store = MemoryStore::File(store.into());
+        self
+    }
+
+    /// 使用已打开的长期记忆，通常与 Agent 的长期记忆相同
+    pub fn with_long_memory(mut self, long_memory: Arc<LongMemory>) -> Self {
+        self.store = MemoryStore::Shared(long_memory);
+        self
+    }
+}
+
+impl Default for MemorySearch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 记忆检索工具：从长期记忆中搜索与查询相关的内容，按相关度从高到低返回
+#[derive(Serialize, Deserialize, Tool)]
+pub struct MemorySearchArgs {
+    /// 需要回忆的内容
+    pub query: String,
+    /// 返回的条数，默认 3，最多 10
+    pub top_k: Option<usize>,
+}
+
+impl TypedTool for MemorySearch {
+    type Args = MemorySearchArgs;
+
+    fn call(&self, args: MemorySearchArgs) -> BoxFuture<'_, Result<String>> {
+        Box::pin(async move {
+            let top_k = args.top_k.unwrap_or(DEFAULT_TOP_K).clamp(1, MAX_TOP_K);
+
+            let memories = self.store.open()?.search(&args.query, top_k).await?;
+
+            if memories.is_empty() {
+                return Ok("没有找到相关的记忆".to_string());
+            }
+
+            Ok(memories
+                .iter()
+                .map(|(entry, score)| {
+                    format!("[{}] (相关度 {:.2}) {}", entry.id, score, entry.content)
+                })
+                .collect::<Vec<_>>()
+                .join("\n"))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::{tool_memory_save::MemorySave, Tool};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_memory_save_and_search() -> Result<()> {
+        let store =
+            std::env::temp_dir().join(format!("my-agent-memory-tools-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&store);
+
+        let search = MemorySearch::new().with_store(&store);
+        let query = json!({ "query": "周杰伦的生日" });
+        assert_eq!(search.execute(query.clone()).await?, "没有找到相关的记忆");
+
+        let save = MemorySave::new().with_store(&store);
+        for content in ["周杰伦的生日是 1979 年 1 月 18 日", "报告已写入 report.md"]
+        {
+            let result = save.execute(json!({ "content": content })).await?;
+            assert!(result.starts_with("保存成功"));
+        }
+
+        let result = search.execute(query).await?;
+        let lines = result.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("[1]"));
+        assert!(lines[0].ends_with("周杰伦的生日是 1979 年 1 月 18 日"));
+
+        // top_k 超出上限时按上限返回
+        let result = search
+            .execute(json!({ "query": "生日", "top_k": 100 }))
+            .await?;
+        assert_eq!(result.lines().count(), 2);
+
+        std::fs::remove_file(&store)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+
+/// 工具的执行结果：content 返回给大模型，sources 供客户端和运行记录使用
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ToolOutput {
+    pub content: String,
+    pub sources: Vec<ToolSource>,
+}
+
+impl ToolOutput {
+    pub fn new(content: impl Into<String>) -> Self {
+        Self {
+            content: content.into(),
+            sources: Vec::new(),
+        }
+    }
+
+    /// 附带结果引用的来源，例如搜索到的网页
+    pub fn with_sources(mut self, sources: Vec<ToolSource>) -> Self {
+        self.sources = sources;
+        self
+    }
+}
+
+impl From<String> for ToolOutput {
+    fn from(content: String) -> Self {
+        Self::new(content)
+    }
+}
+
+/// 工具结果引用的来源
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolSource {
+    pub title: String,
+    pub url: String,
+}
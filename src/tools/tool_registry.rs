@@ -0,0 +1,259 @@
+use super::{
+    json_repair::parse_lenient, tool_file_write::FileWrite, tool_finish::Finish,
+    tool_memory_save::MemorySave, tool_memory_search::MemorySearch, tool_search::Search,
+    tool_validation::validate_arguments, Tool, ToolError,
+};
+use crate::memory::LongMemory;
+use async_openai::{
+    error::OpenAIError,
+    types::{
+        ChatCompletionTool, ChatCompletionToolArgs, ChatCompletionToolType, FunctionCall,
+        FunctionObjectArgs,
+    },
+};
+use serde_json::Value;
+use std::{
+    fmt::{self, Debug},
+    sync::Arc,
+};
+
+/// Agent 可以使用的工具集合，按注册顺序提供给大模型
+///
+/// 结束工具 finish 始终存在，Agent 依靠它结束运行
+#[derive(Clone)]
+pub struct ToolRegistry {
+    tools: Vec<Arc<dyn Tool>>,
+}
+
+impl ToolRegistry {
+    /// 只包含结束工具
+    pub fn new() -> Self {
+        Self {
+            tools: vec![Arc::new(Finish)],
+        }
+    }
+
+    /// 包含全部内置工具：search、file_write、memory_save、memory_search 和 finish
+    pub fn builtin() -> Self {
+        Self::new()
+            .with(Search::new())
+            .with(FileWrite)
+            .with(MemorySave::new())
+            .with(MemorySearch::new())
+    }
+
+    /// 注册工具，同名的工具会被替换
+    pub fn with(mut self, tool: impl Tool + 'static) -> Self {
+        let tool: Arc<dyn Tool> = Arc::new(tool);
+
+        match self.position(tool.name()) {
+            Some(index) => self.tools[index] = tool,
+            // 结束工具保持在最后
+            None => self.tools.insert(self.tools.len() - 1, tool),
+        }
+
+        self
+    }
+
+    /// 已注册的 memory_save 和 memory_search 改为读写指定的长期记忆
+    pub fn with_long_memory(mut self, long_memory: &Arc<LongMemory>) -> Self {
+        if self.position("memory_save").is_some() {
+            self = self.with(MemorySave::new().with_long_memory(long_memory.clone()));
+        }
+        if self.position("memory_search").is_some() {
+            self = self.with(MemorySearch::new().with_long_memory(long_memory.clone()));
+        }
+
+        self
+    }
+
+    /// 移除工具，结束工具不能移除
+    pub fn without(mut self, name: &str) -> Self {
+        if name != "finish" {
+            self.tools.retain(|tool| tool.name() != name);
+        }
+
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn Tool>> {
+        self.position(name).map(|index| self.tools[index].clone())
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.tools.iter().map(|tool| tool.name()).collect()
+    }
+
+    /// 发送给大模型的工具定义
+    pub(crate) fn definitions(&self) -> Vec<ChatCompletionTool> {
+        self.tools
+            .iter()
+            .filter_map(|tool| definition(tool.as_ref()).ok())
+            .collect()
+    }
+
+    /// 按名称找到工具，解析参数并按工具的参数 Schema 检查
+    pub(crate) fn prepare(&self, call: FunctionCall) -> Result<(Arc<dyn Tool>, Value), ToolError> {
+        let tool = self
+            .get(&call.name)
+            .ok_or_else(|| ToolError::unknown_tool(call.name.as_str()))?;
+
+        // 部分模型给出的参数不是严格的 JSON，解析失败时先尝试修复
+        let arguments = parse_lenient(&call.arguments)
+            .map_err(|e| ToolError::invalid_arguments(call.name.as_str(), e))?;
+
+        // 执行前按参数的 Schema 检查，把全部错误一次性告诉大模型
+        let errors = validate_arguments(&tool.parameters(), &arguments);
+        if !errors.is_empty() {
+            return Err(ToolError::invalid_arguments(
+                call.name.as_str(),
+                errors.join("; "),
+            ));
+        }
+
+        Ok((tool, arguments))
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.tools.iter().position(|tool| tool.name() == name)
+    }
+}
+
+impl Default for ToolRegistry {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl Debug for ToolRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
+
+fn definition(tool: &dyn Tool) -> Result<ChatCompletionTool, OpenAIError> {
+    ChatCompletionToolArgs::default()
+        .r#type(ChatCompletionToolType::Function)
+        .function(
+            FunctionObjectArgs::default()
+                .name(tool.name())
+                .description(tool.description())
+                .parameters(tool.parameters())
+                .build()?,
+        )
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::{parse_arguments, ToolErrorKind};
+    use anyhow::Result;
+    use futures::future::BoxFuture;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    struct Echo(&'static str);
+
+    #[derive(Deserialize)]
+    struct EchoArgs {
+        text: String,
+    }
+
+    impl Tool for Echo {
+        fn name(&self) -> &str {
+            "search"
+        }
+
+        fn description(&self) -> &str {
+            self.0
+        }
+
+        fn parameters(&self) -> Value {
+            json!({
+                "type": "object",
+                "properties": { "text": { "type": "string" } },
+                "required": ["text"],
+            })
+        }
+
+        fn execute(&self, arguments: Value) -> BoxFuture<'_, anyhow::Result<String>> {
+            Box::pin(async move {
+                let args: EchoArgs = parse_arguments(self.name(), arguments)?;
+                Ok(args.text)
+            })
+        }
+    }
+
+    fn call(name: &str, arguments: &str) -> FunctionCall {
+        FunctionCall {
+            name: name.to_string(),
+            arguments: arguments.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_tool_registry_builtin() {
+        let registry = ToolRegistry::builtin();
+        assert_eq!(
+            registry.names(),
+            vec![
+                "search",
+                "file_write",
+                "memory_save",
+                "memory_search",
+                "finish"
+            ]
+        );
+
+        let definitions = registry.definitions();
+        assert_eq!(definitions.len(), 5);
+        assert_eq!(definitions[1].function.name, "file_write");
+        assert_eq!(
+            definitions[1].function.parameters.as_ref().unwrap()["required"],
+            json!(["filename", "content"])
+        );
+
+        // 结束工具不能移除
+        let registry = registry.without("search").without("finish");
+        assert_eq!(
+            registry.names(),
+            vec!["file_write", "memory_save", "memory_search", "finish"]
+        );
+        assert_eq!(ToolRegistry::new().names(), vec!["finish"]);
+    }
+
+    #[tokio::test]
+    async fn test_tool_registry_custom_tool() -> Result<()> {
+        // 同名的工具替换内置工具，位置不变
+        let registry = ToolRegistry::builtin().with(Echo("echo"));
+        assert_eq!(registry.names().len(), 5);
+        assert_eq!(registry.get("search").unwrap().description(), "echo");
+
+        let (tool, arguments) = registry.prepare(call("search", r#"{"text":"hi"}"#))?;
+        assert_eq!(tool.execute(arguments).await?, "hi");
+
+        let error = registry.prepare(call("weather", "{}")).err().unwrap();
+        assert_eq!(error.kind, ToolErrorKind::UnknownTool);
+
+        // 常见的格式问题会被修复
+        let (_, arguments) = registry.prepare(call("search", "```json\n{'text': 'hi',}\n```"))?;
+        assert_eq!(arguments, json!({ "text": "hi" }));
+
+        let error = registry.prepare(call("search", "{text:")).err().unwrap();
+        assert_eq!(error.kind, ToolErrorKind::InvalidArguments);
+
+        // 不符合参数 Schema 的调用在执行前被拒绝
+        let error = registry
+            .prepare(call("search", r#"{"text":1}"#))
+            .err()
+            .unwrap();
+        assert_eq!(error.kind, ToolErrorKind::InvalidArguments);
+        assert_eq!(
+            error.message,
+            "`arguments.text` must be string, got integer"
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,299 @@
+use super::{Tool, ToolError, ToolOutput};
+use futures::{Stream, StreamExt};
+use serde_json::Value;
+use std::{sync::Arc, time::Duration};
+use tokio::sync::Mutex;
+
+/// 一次待执行的工具调用，tool 为找不到工具或参数解析失败时直接返回错误
+pub(crate) struct ToolCall {
+    pub id: String,
+    pub name: String,
+    pub tool: Result<(Arc<dyn Tool>, Value), ToolError>,
+    // 执行超时时间，None 表示不限制
+    pub timeout: Option<Duration>,
+}
+
+/// 工具调用的执行结果
+pub(crate) struct ToolOutcome {
+    pub id: String,
+    pub name: String,
+    pub result: Result<ToolOutput, ToolError>,
+}
+
+/// 并发执行一组工具调用，最多同时执行 limit 个，结果按调用顺序返回
+///
+/// 不支持并发的工具（例如写文件）共享同一把锁，按调用顺序依次执行。
+/// 工具在返回的 Stream 中执行而不是单独 spawn，Stream 被丢弃时正在执行的工具随之取消
+pub(crate) fn run_tool_calls(
+    calls: Vec<ToolCall>,
+    limit: usize,
+) -> impl Stream<Item = ToolOutcome> {
+    let serial = Arc::new(Mutex::new(()));
+
+    futures::stream::iter(calls)
+        .map(move |call| {
+            let serial = serial.clone();
+
+            async move {
+                let ToolCall {
+                    id,
+                    name,
+                    tool,
+                    timeout,
+                } = call;
+
+                let result = match tool {
+                    Ok((tool, arguments)) => {
+                        let _guard = match tool.is_concurrent_safe() {
+                            true => None,
+                            false => Some(serial.lock().await),
+                        };
+
+                        // 等待锁的时间不计入超时
+                        let result = match timeout {
+                            Some(timeout) => {
+                                tokio::time::timeout(timeout, tool.execute_output(arguments))
+                                    .await
+                                    .map_err(|_| ToolError::timeout(name.as_str(), timeout))
+                            }
+                            None => Ok(tool.execute_output(arguments).await),
+                        };
+
+                        match result {
+                            Ok(Ok(result)) => Ok(result),
+                            // 工具自行判断的参数错误保留原有的错误类型
+                            Ok(Err(e)) => Err(e
+                                .downcast::<ToolError>()
+                                .unwrap_or_else(|e| ToolError::execution_failed(name.as_str(), e))),
+                            Err(e) => Err(e),
+                        }
+                    }
+                    Err(e) => Err(e),
+                };
+
+                ToolOutcome { id, name, result }
+            }
+        })
+        .buffered(limit.max(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::{anyhow, Result};
+    use futures::future::BoxFuture;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct Counter {
+        running: AtomicUsize,
+        peak: AtomicUsize,
+        cancelled: AtomicBool,
+    }
+
+    // 工具执行的 Future 被丢弃时记录取消
+    struct CancelGuard(Arc<Counter>);
+
+    impl Drop for CancelGuard {
+        fn drop(&mut self) {
+            if std::thread::panicking() {
+                return;
+            }
+            self.0.cancelled.store(true, Ordering::SeqCst);
+        }
+    }
+
+    struct SlowTool {
+        label: &'static str,
+        delay_ms: u64,
+        concurrent_safe: bool,
+        counter: Arc<Counter>,
+    }
+
+    impl Tool for SlowTool {
+        fn name(&self) -> &str {
+            "slow"
+        }
+
+        fn description(&self) -> &str {
+            "slow tool"
+        }
+
+        fn parameters(&self) -> Value {
+            Value::Null
+        }
+
+        fn execute(&self, _arguments: Value) -> BoxFuture<'_, Result<String>> {
+            Box::pin(async move {
+                let running = self.counter.running.fetch_add(1, Ordering::SeqCst) + 1;
+                self.counter.peak.fetch_max(running, Ordering::SeqCst);
+
+                let guard = CancelGuard(self.counter.clone());
+                tokio::time::sleep(Duration::from_millis(self.delay_ms)).await;
+                std::mem::forget(guard);
+
+                self.counter.running.fetch_sub(1, Ordering::SeqCst);
+
+                match self.label {
+                    "fail" => Err(anyhow!("boom")),
+                    "invalid" => Err(ToolError::invalid_arguments("slow", "bad").into()),
+                    label => Ok(label.to_string()),
+                }
+            })
+        }
+
+        fn is_concurrent_safe(&self) -> bool {
+            self.concurrent_safe
+        }
+    }
+
+    fn calls(specs: &[(&'static str, u64, bool)], counter: &Arc<Counter>) -> Vec<ToolCall> {
+        specs
+            .iter()
+            .enumerate()
+            .map(|(index, (label, delay_ms, concurrent_safe))| ToolCall {
+                id: format!("call_{}", index),
+                name: "slow".to_string(),
+                tool: Ok((
+                    Arc::new(SlowTool {
+                        label,
+                        delay_ms: *delay_ms,
+                        concurrent_safe: *concurrent_safe,
+                        counter: counter.clone(),
+                    }) as Arc<dyn Tool>,
+                    Value::Null,
+                )),
+                timeout: None,
+            })
+            .collect()
+    }
+
+    async fn run(calls: Vec<ToolCall>, limit: usize) -> Vec<(String, String)> {
+        run_tool_calls(calls, limit)
+            .map(|outcome| {
+                let result = match outcome.result {
+                    Ok(output) => output.content,
+                    Err(e) => e.to_content(),
+                };
+                (outcome.id, result)
+            })
+            .collect()
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_run_tool_calls_preserves_order() {
+        let counter = Arc::new(Counter::default());
+        let calls = calls(
+            &[("a", 60, true), ("b", 10, true), ("c", 30, true)],
+            &counter,
+        );
+
+        let results = run(calls, 4).await;
+
+        assert_eq!(
+            results,
+            vec![
+                ("call_0".to_string(), "a".to_string()),
+                ("call_1".to_string(), "b".to_string()),
+                ("call_2".to_string(), "c".to_string()),
+            ]
+        );
+        assert_eq!(counter.peak.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_run_tool_calls_respects_limit() {
+        let counter = Arc::new(Counter::default());
+        let calls = calls(
+            &[
+                ("a", 20, true),
+                ("b", 20, true),
+                ("c", 20, true),
+                ("d", 20, true),
+            ],
+            &counter,
+        );
+
+        run(calls, 2).await;
+
+        assert_eq!(counter.peak.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_run_tool_calls_serializes_unsafe_tools() {
+        let counter = Arc::new(Counter::default());
+        let calls = calls(
+            &[("a", 20, false), ("b", 20, false), ("c", 20, false)],
+            &counter,
+        );
+
+        let results = run(calls, 4).await;
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(counter.peak.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_run_tool_calls_reports_errors() {
+        let counter = Arc::new(Counter::default());
+        let mut calls = calls(
+            &[("fail", 0, true), ("ok", 0, true), ("invalid", 0, true)],
+            &counter,
+        );
+        calls.push(ToolCall {
+            id: "call_3".to_string(),
+            name: "unknown".to_string(),
+            tool: Err(ToolError::unknown_tool("unknown")),
+            timeout: None,
+        });
+
+        let results = run(calls, 4).await;
+
+        assert_eq!(
+            results[0].1,
+            ToolError::execution_failed("slow", "boom").to_content()
+        );
+        assert_eq!(results[1].1, "ok");
+        assert_eq!(
+            results[2].1,
+            ToolError::invalid_arguments("slow", "bad").to_content()
+        );
+        assert_eq!(
+            results[3].1,
+            ToolError::unknown_tool("unknown").to_content()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_tool_calls_times_out() {
+        let counter = Arc::new(Counter::default());
+        let mut calls = calls(&[("slow", 1_000, true), ("fast", 0, true)], &counter);
+        calls[0].timeout = Some(Duration::from_millis(20));
+        calls[1].timeout = Some(Duration::from_millis(20));
+
+        let results = run(calls, 4).await;
+
+        assert_eq!(
+            results[0].1,
+            ToolError::timeout("slow", Duration::from_millis(20)).to_content()
+        );
+        assert_eq!(results[1].1, "fast");
+        assert!(counter.cancelled.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_run_tool_calls_cancelled_when_dropped() {
+        let counter = Arc::new(Counter::default());
+        let calls = calls(&[("slow", 1_000, true)], &counter);
+
+        let mut outcomes = Box::pin(run_tool_calls(calls, 4));
+        let polled = tokio::time::timeout(Duration::from_millis(20), outcomes.next()).await;
+        assert!(polled.is_err());
+        assert!(!counter.cancelled.load(Ordering::SeqCst));
+
+        // 丢弃 Stream 时正在执行的工具随之取消
+        drop(outcomes);
+        assert!(counter.cancelled.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,276 @@
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+
+/// 可以作为工具参数的类型及其 JSON Schema
+pub trait ToolParameter {
+    /// 是否为可选参数，可选参数不出现在 required 中
+    const OPTIONAL: bool = false;
+
+    fn schema() -> Value;
+}
+
+macro_rules! impl_tool_parameter {
+    ($kind:literal => $($ty:ty),+) => {
+        $(
+            impl ToolParameter for $ty {
+                fn schema() -> Value {
+                    json!({ "type": $kind })
+                }
+            }
+        )+
+    };
+}
+
+impl_tool_parameter!("string" => String, char);
+impl_tool_parameter!("integer" => i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+impl_tool_parameter!("number" => f32, f64);
+impl_tool_parameter!("boolean" => bool);
+
+// 可选参数也可以为 null
+impl<T: ToolParameter> ToolParameter for Option<T> {
+    const OPTIONAL: bool = true;
+
+    fn schema() -> Value {
+        let mut schema = T::schema();
+
+        match schema.get_mut("type") {
+            Some(Value::String(kind)) => {
+                schema["type"] = json!([kind.clone(), "null"]);
+            }
+            Some(Value::Array(kinds)) if !kinds.contains(&json!("null")) => {
+                kinds.push(json!("null"));
+            }
+            _ => {}
+        }
+
+        schema
+    }
+}
+
+impl<T: ToolParameter> ToolParameter for Vec<T> {
+    fn schema() -> Value {
+        json!({ "type": "array", "items": T::schema() })
+    }
+}
+
+impl<T: ToolParameter> ToolParameter for HashMap<String, T> {
+    fn schema() -> Value {
+        json!({ "type": "object", "additionalProperties": T::schema() })
+    }
+}
+
+impl<T: ToolParameter> ToolParameter for BTreeMap<String, T> {
+    fn schema() -> Value {
+        json!({ "type": "object", "additionalProperties": T::schema() })
+    }
+}
+
+// 任意 JSON 值，不限制类型
+impl ToolParameter for Value {
+    fn schema() -> Value {
+        json!({})
+    }
+}
+
+/// 供 `#[derive(Tool)]` 生成的代码使用，不属于公开接口
+#[doc(hidden)]
+pub mod __private {
+    use super::*;
+
+    pub use serde_json::Value;
+
+    /// 在类型的 Schema 上加入字段声明的校验关键字
+    pub fn constrain(mut schema: Value, constraints: Vec<(&str, Value)>) -> Value {
+        if let Some(object) = schema.as_object_mut() {
+            for (keyword, value) in constraints {
+                object.insert(keyword.to_string(), value);
+            }
+        }
+
+        schema
+    }
+
+    /// 由字段名称、说明、Schema 和是否可选生成参数结构的 Schema
+    pub fn object_schema(fields: Vec<(&str, Option<&str>, Value, bool)>) -> Value {
+        let mut properties = Map::new();
+        let mut required = Vec::new();
+
+        for (name, description, mut schema, optional) in fields {
+            if let (Some(description), Some(schema)) = (description, schema.as_object_mut()) {
+                schema.insert("description".to_string(), description.into());
+            }
+            properties.insert(name.to_string(), schema);
+
+            if !optional {
+                required.push(name);
+            }
+        }
+
+        json!({
+            "type": "object",
+            "properties": properties,
+            "required": required,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::{Tool, ToolArgs, ToolError, ToolErrorKind, TypedTool};
+    use anyhow::Result;
+    use futures::future::BoxFuture;
+    use serde::Deserialize;
+
+    /// 查询城市的天气
+    ///
+    /// 返回气温和天气状况
+    #[derive(Deserialize, crate::tools::Tool)]
+    struct WeatherForecastArgs {
+        /// 城市名称
+        city: String,
+        /// 预报的天数
+        #[tool(minimum = 1, maximum = 7)]
+        days: Option<u8>,
+        #[tool(enum = ["c", "f"])]
+        unit: Option<String>,
+        tags: Vec<String>,
+    }
+
+    /// 回显输入的内容
+    #[derive(Debug, Deserialize, crate::tools::Tool)]
+    #[tool(name = "echo")]
+    struct EchoArgs {
+        #[tool(min_length = 1, max_length = 100)]
+        text: String,
+    }
+
+    /// 创建日程
+    #[derive(Debug, Deserialize, crate::tools::Tool)]
+    #[serde(rename_all = "camelCase")]
+    struct CreateEventArgs {
+        event_title: String,
+        #[serde(rename = "start")]
+        start_time: String,
+        #[serde(default)]
+        all_day: bool,
+        #[serde(skip)]
+        calendar_id: u64,
+    }
+
+    struct WeatherForecast;
+
+    impl TypedTool for WeatherForecast {
+        type Args = WeatherForecastArgs;
+
+        fn call(&self, args: WeatherForecastArgs) -> BoxFuture<'_, Result<String>> {
+            Box::pin(async move {
+                Ok(format!(
+                    "{} 未来 {} 天晴 {:?} {}",
+                    args.city,
+                    args.days.unwrap_or(1),
+                    args.tags,
+                    args.unit.as_deref().unwrap_or("c")
+                ))
+            })
+        }
+    }
+
+    #[test]
+    fn test_derive_tool_args() {
+        assert_eq!(WeatherForecastArgs::NAME, "weather_forecast");
+        assert_eq!(
+            WeatherForecastArgs::DESCRIPTION,
+            "查询城市的天气\n\n返回气温和天气状况"
+        );
+        assert_eq!(
+            WeatherForecastArgs::schema(),
+            json!({
+                "type": "object",
+                "properties": {
+                    "city": { "type": "string", "description": "城市名称" },
+                    "days": {
+                        "type": ["integer", "null"],
+                        "description": "预报的天数",
+                        "minimum": 1,
+                        "maximum": 7,
+                    },
+                    "unit": { "type": ["string", "null"], "enum": ["c", "f"] },
+                    "tags": { "type": "array", "items": { "type": "string" } },
+                },
+                "required": ["city", "tags"],
+            })
+        );
+
+        assert_eq!(EchoArgs::NAME, "echo");
+        assert_eq!(EchoArgs::DESCRIPTION, "回显输入的内容");
+        assert_eq!(
+            EchoArgs::schema()["properties"]["text"],
+            json!({ "type": "string", "minLength": 1, "maxLength": 100 })
+        );
+    }
+
+    #[test]
+    fn test_derive_tool_args_with_serde() -> Result<()> {
+        assert_eq!(
+            CreateEventArgs::schema(),
+            json!({
+                "type": "object",
+                "properties": {
+                    "eventTitle": { "type": "string" },
+                    "start": { "type": "string" },
+                    "allDay": { "type": "boolean" },
+                },
+                "required": ["eventTitle", "start"],
+            })
+        );
+
+        let args = CreateEventArgs::parse(json!({ "eventTitle": "周会", "start": "10:00" }))?;
+        assert_eq!(args.event_title, "周会");
+        assert_eq!(args.start_time, "10:00");
+        assert!(!args.all_day);
+        assert_eq!(args.calendar_id, 0);
+
+        assert_eq!(
+            <Option<Vec<String>>>::schema(),
+            json!({ "type": ["array", "null"], "items": { "type": "string" } })
+        );
+        assert_eq!(<Option<Value>>::schema(), json!({}));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_tool_args() -> Result<()> {
+        assert_eq!(EchoArgs::parse(json!({ "text": "你好" }))?.text, "你好");
+
+        let error = EchoArgs::parse(json!({ "text": 1 }))
+            .unwrap_err()
+            .downcast::<ToolError>()?;
+        assert_eq!(error.kind, ToolErrorKind::InvalidArguments);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_typed_tool() -> Result<()> {
+        let tool = WeatherForecast;
+        assert_eq!(tool.name(), "weather_forecast");
+        assert_eq!(tool.parameters(), WeatherForecastArgs::schema());
+
+        let result = tool
+            .execute(json!({ "city": "北京", "days": 2, "tags": ["空气"] }))
+            .await?;
+        assert_eq!(result, r#"北京 未来 2 天晴 ["空气"] c"#);
+
+        // 参数与 Schema 不符时返回参数错误
+        let error = tool
+            .execute(json!({ "city": "北京" }))
+            .await
+            .unwrap_err()
+            .downcast::<ToolError>()?;
+        assert_eq!(error.kind, ToolErrorKind::InvalidArguments);
+
+        Ok(())
+    }
+}
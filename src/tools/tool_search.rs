@@ -1,125 +1,151 @@
 use super::{
-    search::tavily::{SearchParameters, Tavily},
-    ToolExector, ToolPrompt,
+    search::tavily::{SearchParameters, SearchResponse, Tavily},
+    Tool, ToolOutput, ToolSource, TypedTool,
 };
+use crate::cassette::{Cassette, CassetteMode};
 use anyhow::{anyhow, Result};
-use async_openai::{
-    error::OpenAIError,
-    types::{
-        ChatCompletionTool, ChatCompletionToolArgs, ChatCompletionToolType, FunctionCall,
-        FunctionObjectArgs,
-    },
-};
+use futures::future::BoxFuture;
 use serde::{Deserialize, Serialize};
-use serde_json::json;
-use std::fmt::{self, Debug};
+use std::sync::Arc;
 
-#[derive(Default)]
+/// 搜索工具：通过 Tavily 搜索互联网上的内容
+#[derive(Debug, Default, Clone)]
 pub struct Search {
-    client: Option<Tavily>,
-    query: String,
+    cassette: Option<Arc<Cassette>>,
 }
 
 impl Search {
-    pub fn new(query: String) -> Self {
-        let api_key = std::env::var("TAVILY_API_KEY").expect("Missing TAVILY_API_KEY");
-        let client = Tavily::new(api_key);
-
-        Self {
-            client: Some(client),
-            query,
-        }
+    pub fn new() -> Self {
+        Self::default()
     }
-}
 
-impl ToolExector for Search {
-    async fn execute(&self) -> Result<String> {
-        let params = SearchParameters::builder().query(&self.query).build()?;
+    /// 使用磁带录制或回放搜索请求
+    pub fn with_cassette(mut self, cassette: Arc<Cassette>) -> Self {
+        self.cassette = Some(cassette);
+        self
+    }
 
-        let client = self
-            .client
+    fn client(&self) -> Result<Tavily> {
+        let replaying = self
+            .cassette
             .as_ref()
-            .ok_or_else(|| anyhow!("Client not initialized"))?;
-
-        let response = client.search(params).await?;
+            .is_some_and(|cassette| cassette.mode() == CassetteMode::Replay);
+
+        // 回放磁带时不会访问网络，因此不强制要求 TAVILY_API_KEY
+        let api_key = match std::env::var("TAVILY_API_KEY") {
+            Ok(api_key) => api_key,
+            Err(_) if replaying => String::new(),
+            Err(_) => return Err(anyhow!("Missing TAVILY_API_KEY")),
+        };
+
+        let mut client = Tavily::new(api_key);
+        if let Some(cassette) = &self.cassette {
+            client = client.with_cassette(cassette.clone());
+        }
 
-        Ok(format!("{}", response))
+        Ok(client)
     }
-}
 
-impl ToolPrompt for Search {
-    fn command(&self) -> String {
-        r#"{
-            "name": "search",
-            "description": "这是一个搜索引擎，当你已有的知识不足以完成目标任务时，可以通过它获取互联网上的信息",
-            "args": [
-                {
-                    "name": "query",
-                    "type": "string",
-                    "description": "需要搜索的内容"
-                }
-            ]
-        }"#.to_string()
-    }
+    async fn search(&self, args: SearchArgs) -> Result<SearchResponse> {
+        let params = SearchParameters::builder().query(&args.query).build()?;
 
-    fn resource(&self) -> String {
-        "上网搜索和收集信息".to_string()
+        self.client()?.search(params).await
     }
 }
 
-impl Debug for Search {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("Search")
-            .field("query", &self.query)
-            .finish()
-    }
+/// 通过搜索引擎搜索互联网上的内容。
+///
+/// 当你的知识无法回答用户提出的问题，或用户请求你进行联网搜索时，调用此工具。请从与用户的对话中提取用户想要搜索的内容作为 query 参数的值。
+/// 搜索结果包含网站的标题、网站的地址（URL）以及网站简介。
+#[derive(Serialize, Deserialize, Tool)]
+pub struct SearchArgs {
+    /// 用户搜索的内容，请从用户的提问或聊天上下文中提取。
+    #[tool(min_length = 1)]
+    pub query: String,
 }
 
-impl TryFrom<Search> for ChatCompletionTool {
-    type Error = OpenAIError;
-
-    fn try_from(_search: Search) -> Result<Self, Self::Error> {
-        ChatCompletionToolArgs::default()
-            .r#type(ChatCompletionToolType::Function)
-            .function(
-                FunctionObjectArgs::default()
-                    .name("search")
-                    .description(r#"
-                        通过搜索引擎搜索互联网上的内容。
-
-                        当你的知识无法回答用户提出的问题，或用户请求你进行联网搜索时，调用此工具。请从与用户的对话中提取用户想要搜索的内容作为 query 参数的值。
-                        搜索结果包含网站的标题、网站的地址（URL）以及网站简介。
-                    "#)
-                    .parameters(json!({
-                        "type": "object",
-                        "properties": {
-                            "query": {
-                                "type": "string",
-                                "description": "用户搜索的内容，请从用户的提问或聊天上下文中提取。",
-                            }
-                        },
-                        "required": ["query"],
-                    }))
-                    .build()?,
-            )
-            .build()
+impl TypedTool for Search {
+    type Args = SearchArgs;
+
+    fn call(&self, args: SearchArgs) -> BoxFuture<'_, Result<String>> {
+        Box::pin(async move { Ok(format!("{}", self.search(args).await?)) })
     }
-}
 
-#[derive(Serialize, Deserialize)]
-struct SearchArgs {
-    query: String,
+    // 搜索结果的标题和链接作为来源，不写入返回给大模型的内容
+    fn call_output(&self, args: SearchArgs) -> BoxFuture<'_, Result<ToolOutput>> {
+        Box::pin(async move {
+            let response = self.search(args).await?;
+            let sources = response
+                .results
+                .iter()
+                .map(|item| ToolSource {
+                    title: item.title.clone(),
+                    url: item.url.clone(),
+                })
+                .collect();
+
+            Ok(ToolOutput::new(format!("{}", response)).with_sources(sources))
+        })
+    }
 }
 
-impl TryFrom<FunctionCall> for Search {
-    type Error = anyhow::Error;
-
-    fn try_from(call: FunctionCall) -> Result<Self, Self::Error> {
-        if call.name == "search" {
-            let args: SearchArgs = serde_json::from_str(&call.arguments)?;
-            Ok(Search::new(args.query))
-        } else {
-            Err(anyhow!("Invalid function call: {:?}", call))
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::{
+        search::tavily::{cassette_request, SearchItem},
+        ToolRegistry,
+    };
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_search_with_cassette() -> Result<()> {
+        let path = std::env::temp_dir().join(format!(
+            "my-agent-search-cassette-{}.json",
+            std::process::id()
+        ));
+
+        // Tavily 客户端每次只取一条结果
+        let params = SearchParameters::builder()
+            .query("Context Caching")
+            .max_results(1_usize)
+            .build()?;
+        let response = SearchResponse {
+            answer: None,
+            query: Some("Context Caching".to_string()),
+            response_time: Some(0.5),
+            images: vec![],
+            results: vec![SearchItem {
+                title: "Context Caching".to_string(),
+                url: "https://example.com".to_string(),
+                content: "Context Caching 是一种缓存技术".to_string(),
+                raw_content: None,
+                score: 0.9,
+            }],
+        };
+
+        let cassette = Cassette::record(&path)?;
+        cassette.record_interaction("tavily", &cassette_request(&params)?, &response)?;
+
+        // 磁带随工具注册，不影响其他搜索工具
+        let tools = ToolRegistry::builtin()
+            .with(Search::new().with_cassette(Arc::new(Cassette::replay(&path)?)));
+        let output = tools
+            .get("search")
+            .unwrap()
+            .execute_output(json!({ "query": "Context Caching" }))
+            .await?;
+
+        assert_eq!(output.content, "Context Caching 是一种缓存技术\n");
+        assert_eq!(
+            output.sources,
+            vec![ToolSource {
+                title: "Context Caching".to_string(),
+                url: "https://example.com".to_string(),
+            }]
+        );
+
+        std::fs::remove_file(&path)?;
+        Ok(())
     }
 }
@@ -1,13 +1,104 @@
+use super::{ToolError, ToolOutput};
 use anyhow::Result;
-use enum_dispatch::enum_dispatch;
+use futures::future::BoxFuture;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
 
-#[enum_dispatch]
-pub trait ToolExector {
-    async fn execute(&self) -> Result<String>;
+/// 可供大模型调用的工具，注册到 ToolRegistry 后由 Agent 按名称调用
+pub trait Tool: Send + Sync {
+    /// 工具名称，在同一个 ToolRegistry 中唯一
+    fn name(&self) -> &str;
+
+    /// 工具的用途说明，大模型据此决定何时调用
+    fn description(&self) -> &str;
+
+    /// 参数的 JSON Schema
+    fn parameters(&self) -> Value;
+
+    /// 执行工具，arguments 为大模型给出的参数
+    fn execute(&self, arguments: Value) -> BoxFuture<'_, Result<String>>;
+
+    /// 执行工具并返回附带来源等结构化数据的结果，默认只包含 execute 的返回值
+    fn execute_output(&self, arguments: Value) -> BoxFuture<'_, Result<ToolOutput>> {
+        let result = self.execute(arguments);
+        Box::pin(async move { result.await.map(ToolOutput::from) })
+    }
+
+    /// 是否可以与其他工具调用并发执行，需要按顺序执行的工具返回 false
+    fn is_concurrent_safe(&self) -> bool {
+        true
+    }
+}
+
+/// 将参数反序列化为工具的参数结构，失败时返回参数错误，而不是执行错误
+pub fn parse_arguments<T: DeserializeOwned>(tool: &str, arguments: Value) -> Result<T> {
+    serde_json::from_value(arguments).map_err(|e| ToolError::invalid_arguments(tool, e).into())
+}
+
+/// 工具的参数结构，通常由 `#[derive(Tool)]` 根据字段和文档注释生成
+pub trait ToolArgs: DeserializeOwned + Send {
+    /// 工具名称
+    const NAME: &'static str;
+
+    /// 工具的用途说明
+    const DESCRIPTION: &'static str;
+
+    /// 参数的 JSON Schema
+    fn schema() -> Value;
+
+    /// 将大模型给出的参数解析为参数结构
+    fn parse(arguments: Value) -> Result<Self> {
+        parse_arguments(Self::NAME, arguments)
+    }
+}
+
+/// 参数类型明确的工具，名称、说明和 Schema 都来自参数结构，自动实现 Tool
+pub trait TypedTool: Send + Sync {
+    type Args: ToolArgs;
+
+    /// 使用解析后的参数执行工具
+    fn call(&self, args: Self::Args) -> BoxFuture<'_, Result<String>>;
+
+    /// 见 Tool::execute_output
+    fn call_output(&self, args: Self::Args) -> BoxFuture<'_, Result<ToolOutput>> {
+        let result = self.call(args);
+        Box::pin(async move { result.await.map(ToolOutput::from) })
+    }
+
+    /// 见 Tool::is_concurrent_safe
+    fn is_concurrent_safe(&self) -> bool {
+        true
+    }
 }
 
-#[enum_dispatch]
-pub trait ToolPrompt {
-    fn command(&self) -> String;
-    fn resource(&self) -> String;
+impl<T: TypedTool> Tool for T {
+    fn name(&self) -> &str {
+        T::Args::NAME
+    }
+
+    fn description(&self) -> &str {
+        T::Args::DESCRIPTION
+    }
+
+    fn parameters(&self) -> Value {
+        T::Args::schema()
+    }
+
+    fn execute(&self, arguments: Value) -> BoxFuture<'_, Result<String>> {
+        match T::Args::parse(arguments) {
+            Ok(args) => self.call(args),
+            Err(e) => Box::pin(async move { Err(e) }),
+        }
+    }
+
+    fn execute_output(&self, arguments: Value) -> BoxFuture<'_, Result<ToolOutput>> {
+        match T::Args::parse(arguments) {
+            Ok(args) => self.call_output(args),
+            Err(e) => Box::pin(async move { Err(e) }),
+        }
+    }
+
+    fn is_concurrent_safe(&self) -> bool {
+        TypedTool::is_concurrent_safe(self)
+    }
 }
@@ -0,0 +1,231 @@
+use serde_json::{Map, Value};
+
+/// 按工具声明的 JSON Schema 检查参数，返回全部不符合之处
+///
+/// 支持 type、required、properties、additionalProperties、items、enum、minLength、maxLength、
+/// minimum 和 maximum，其余关键字忽略。错误信息会返回给大模型，便于它修正参数
+pub(crate) fn validate_arguments(schema: &Value, arguments: &Value) -> Vec<String> {
+    let mut errors = Vec::new();
+    validate(schema, arguments, "arguments", &mut errors);
+    errors
+}
+
+fn validate(schema: &Value, value: &Value, path: &str, errors: &mut Vec<String>) {
+    let Some(schema) = schema.as_object() else {
+        return;
+    };
+
+    if let Some(expected) = schema.get("type") {
+        let types = match expected {
+            Value::String(kind) => vec![kind.as_str()],
+            Value::Array(kinds) => kinds.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+
+        if !types.is_empty() && !types.iter().any(|kind| is_type(value, kind)) {
+            errors.push(format!(
+                "`{}` must be {}, got {}",
+                path,
+                types.join(" or "),
+                type_name(value)
+            ));
+            // 类型不对时其余约束没有意义
+            return;
+        }
+    }
+
+    if let Some(Value::Array(allowed)) = schema.get("enum") {
+        if !allowed.contains(value) {
+            let allowed = allowed
+                .iter()
+                .map(Value::to_string)
+                .collect::<Vec<_>>()
+                .join(", ");
+            errors.push(format!(
+                "`{}` must be one of {}, got {}",
+                path, allowed, value
+            ));
+        }
+    }
+
+    match value {
+        Value::String(text) => validate_length(schema, text, path, errors),
+        Value::Number(number) => validate_range(schema, number.as_f64(), path, errors),
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for (index, item) in items.iter().enumerate() {
+                    validate(item_schema, item, &format!("{}[{}]", path, index), errors);
+                }
+            }
+        }
+        Value::Object(object) => validate_object(schema, object, path, errors),
+        _ => {}
+    }
+}
+
+fn validate_object(
+    schema: &Map<String, Value>,
+    object: &Map<String, Value>,
+    path: &str,
+    errors: &mut Vec<String>,
+) {
+    let properties = schema.get("properties").and_then(Value::as_object);
+    let required = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|required| {
+            required
+                .iter()
+                .filter_map(Value::as_str)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    for name in &required {
+        if object.get(*name).is_none_or(Value::is_null) {
+            errors.push(format!("`{}.{}` is required", path, name));
+        }
+    }
+
+    for (name, value) in object {
+        let field = format!("{}.{}", path, name);
+
+        match properties.and_then(|properties| properties.get(name)) {
+            // 大模型常用 null 表示省略可选参数，反序列化时也按省略处理
+            Some(_) if value.is_null() && !required.contains(&name.as_str()) => {}
+            Some(property) => validate(property, value, &field, errors),
+            None => match schema.get("additionalProperties") {
+                Some(Value::Bool(false)) => {
+                    errors.push(format!("`{}` is not allowed", field));
+                }
+                Some(additional) => validate(additional, value, &field, errors),
+                None => {}
+            },
+        }
+    }
+}
+
+fn validate_length(schema: &Map<String, Value>, text: &str, path: &str, errors: &mut Vec<String>) {
+    // 按字符计算长度，与 JSON Schema 一致
+    let length = text.chars().count() as u64;
+
+    if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+        if length < min {
+            errors.push(format!(
+                "`{}` must be at least {} characters, got {}",
+                path, min, length
+            ));
+        }
+    }
+
+    if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+        if length > max {
+            errors.push(format!(
+                "`{}` must be at most {} characters, got {}",
+                path, max, length
+            ));
+        }
+    }
+}
+
+fn validate_range(
+    schema: &Map<String, Value>,
+    number: Option<f64>,
+    path: &str,
+    errors: &mut Vec<String>,
+) {
+    let Some(number) = number else {
+        return;
+    };
+
+    if let Some(minimum) = schema.get("minimum").and_then(Value::as_f64) {
+        if number < minimum {
+            errors.push(format!("`{}` must be >= {}, got {}", path, minimum, number));
+        }
+    }
+
+    if let Some(maximum) = schema.get("maximum").and_then(Value::as_f64) {
+        if number > maximum {
+            errors.push(format!("`{}` must be <= {}, got {}", path, maximum, number));
+        }
+    }
+}
+
+fn is_type(value: &Value, kind: &str) -> bool {
+    match kind {
+        "string" => value.is_string(),
+        // 2.0 这样带小数点的数字无法反序列化为整数
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "boolean" => value.is_boolean(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        "null" => value.is_null(),
+        // 不认识的类型不做限制
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(number) if number.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_validate_arguments() {
+        // 参数按名称顺序检查
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "city": { "type": "string", "minLength": 1, "maxLength": 4 },
+                "unit": { "type": "string", "enum": ["c", "f"] },
+                "days": { "type": "integer", "minimum": 1, "maximum": 7 },
+                "tags": { "type": "array", "items": { "type": "string" } },
+            },
+            "required": ["city"],
+            "additionalProperties": false,
+        });
+
+        let valid = json!({ "city": "北京", "unit": "c", "days": 3, "tags": ["空气"] });
+        assert!(validate_arguments(&schema, &valid).is_empty());
+        // 可选参数可以为 null
+        assert!(validate_arguments(&schema, &json!({ "city": "北京", "days": null })).is_empty());
+
+        assert_eq!(
+            validate_arguments(&schema, &json!({ "unit": "k", "days": 2.5 })),
+            vec![
+                "`arguments.city` is required",
+                "`arguments.days` must be integer, got number",
+                r#"`arguments.unit` must be one of "c", "f", got "k""#,
+            ]
+        );
+        assert_eq!(
+            validate_arguments(
+                &schema,
+                &json!({ "city": "乌鲁木齐市区", "days": 10, "tags": ["a", 1], "lang": "zh" })
+            ),
+            vec![
+                "`arguments.city` must be at most 4 characters, got 6",
+                "`arguments.days` must be <= 7, got 10",
+                "`arguments.lang` is not allowed",
+                "`arguments.tags[1]` must be string, got integer",
+            ]
+        );
+        assert_eq!(
+            validate_arguments(&schema, &json!(["北京"])),
+            vec!["`arguments` must be object, got array"]
+        );
+    }
+}
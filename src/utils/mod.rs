@@ -0,0 +1,3 @@
+mod hash;
+
+pub(crate) use hash::fnv1a;